@@ -0,0 +1,133 @@
+// Language-aware chunking for embeddings.
+//
+// Splits file content into token-bounded segments before embedding so that
+// large files get multiple, range-scoped vectors instead of one coarse
+// whole-file vector. Token counts are approximated as chars/4, which is
+// close enough for packing decisions without pulling in a real tokenizer.
+
+use std::path::Path;
+
+const CHARS_PER_TOKEN: usize = 4;
+const TARGET_TOKENS: usize = 512;
+const OVERLAP_TOKENS: usize = 64;
+
+/// A single chunk of a file's content, with its byte range in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub start_byte: usize,
+    pub end_byte: usize,
+    pub text: String,
+}
+
+fn is_code_extension(ext: &str) -> bool {
+    matches!(
+        ext,
+        "rs" | "py" | "js" | "jsx" | "ts" | "tsx" | "go" | "java" | "c" | "cpp" | "h" | "hpp"
+    )
+}
+
+/// Find structural break points: for code, `fn`/`class`/`def` (and similar)
+/// headers at any indentation and blank-line blocks; for markdown, heading
+/// lines; otherwise just blank-line paragraph boundaries.
+fn structural_boundaries(text: &str, ext: &str) -> Vec<usize> {
+    let is_markdown = matches!(ext, "md" | "markdown" | "mdx");
+    let mut boundaries = vec![0usize];
+    let mut offset = 0usize;
+    let mut prev_blank = false;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_end_matches('\n').trim_start();
+        if is_markdown && trimmed.starts_with('#') {
+            boundaries.push(offset);
+        } else if is_code_extension(ext)
+            && (trimmed.starts_with("fn ")
+                || trimmed.starts_with("pub fn ")
+                || trimmed.starts_with("async fn ")
+                || trimmed.starts_with("class ")
+                || trimmed.starts_with("def ")
+                || trimmed.starts_with("pub struct ")
+                || trimmed.starts_with("struct ")
+                || trimmed.starts_with("impl "))
+        {
+            boundaries.push(offset);
+        } else if trimmed.is_empty() {
+            if !prev_blank {
+                boundaries.push(offset + line.len());
+            }
+            prev_blank = true;
+            offset += line.len();
+            continue;
+        }
+        prev_blank = false;
+        offset += line.len();
+    }
+    boundaries.push(text.len());
+    boundaries.sort_unstable();
+    boundaries.dedup();
+    boundaries
+}
+
+pub(crate) fn nearest_char_boundary(text: &str, mut idx: usize) -> usize {
+    while idx < text.len() && !text.is_char_boundary(idx) {
+        idx += 1;
+    }
+    idx.min(text.len())
+}
+
+/// Greedily pack structural segments into chunks bounded by an approximate
+/// token budget, overlapping each new chunk with the tail of the previous
+/// one so context survives a chunk boundary.
+pub fn chunk_text(text: &str, extension: &str) -> Vec<Chunk> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+
+    let max_chars = TARGET_TOKENS * CHARS_PER_TOKEN;
+    let overlap_chars = OVERLAP_TOKENS * CHARS_PER_TOKEN;
+    let boundaries = structural_boundaries(text, extension);
+
+    let mut chunks = Vec::new();
+    let mut start = 0usize;
+
+    while start < text.len() {
+        // Walk forward through structural boundaries while we still fit the budget.
+        let mut end = start;
+        for &candidate in boundaries.iter().filter(|&&b| b > start) {
+            if candidate - start > max_chars {
+                break;
+            }
+            end = candidate;
+        }
+        if end <= start {
+            end = nearest_char_boundary(text, (start + max_chars).min(text.len()));
+        }
+
+        chunks.push(Chunk {
+            start_byte: start,
+            end_byte: end,
+            text: text[start..end].to_string(),
+        });
+
+        if end >= text.len() {
+            break;
+        }
+
+        let next_start = nearest_char_boundary(text, end.saturating_sub(overlap_chars));
+        start = if next_start > start { next_start } else { end };
+    }
+
+    chunks
+}
+
+/// Lowercased file extension used to pick a chunking strategy. Synthetic
+/// per-record paths (`data.csv#row=42`) are stripped back to the real file
+/// first, since a structured-document row is already one flattened record
+/// rather than a file to be code/markdown-aware chunked.
+pub fn extension_of(path: &str) -> String {
+    let base = path.split_once("#row=").map(|(p, _)| p).unwrap_or(path);
+    Path::new(base)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
@@ -5,18 +5,27 @@ use std::fs;
 use std::path::Path;
 use walkdir::WalkDir;
 use chrono::{DateTime, Local};
-use rand::Rng;
+use rand::{Rng, RngCore};
 use std::hash::{Hash, Hasher};
 use std::collections::hash_map::DefaultHasher;
+use async_trait::async_trait;
 
 // Import git_assistant module from crate root
 use crate::git_assistant;
+use crate::chunking;
+use crate::structured_docs;
+use crate::store::{self, Store};
 
 // Azure OpenAI Configuration
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct AzureConfig {
     pub endpoint: String,           // e.g., "https://your-resource.openai.azure.com"
-    pub api_key: String,            // Your API key
+    // Never written to the config file - lives in the OS keychain, or its
+    // encrypted fallback file (see `store_secret`).
+    // `#[serde(default)]` lets this still deserialize from configs saved
+    // before the keychain migration, which had it inline.
+    #[serde(default, skip_serializing)]
+    pub api_key: String,
     pub deployment_name: String,    // e.g., "text-embedding-ada-002"
     pub api_version: String,        // e.g., "2024-02-01"
 }
@@ -31,9 +40,124 @@ pub struct GcpConfig {
     pub endpoint: Option<String>,
 }
 
+// Secret storage for provider credentials (Azure `api_key`, the contents of
+// a GCP service account JSON). `azure_config.json`/`gcp_config.json` hold
+// only non-secret fields - the secret itself is kept out of the index
+// directory entirely, in the OS keychain, and only falls back to an
+// AES-256-GCM-encrypted file alongside the config when no platform keychain
+// is available (e.g. a headless CI box). The fallback's key lives outside
+// the index directory so copying just the index folder doesn't leak it.
+
+fn secret_service_name(index_path: &Path, provider: &str) -> String {
+    format!("wayfinder-{}-{}", provider, content_hash(&index_path.to_string_lossy()))
+}
+
+fn secret_fallback_key_path() -> std::path::PathBuf {
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .unwrap_or_else(|_| ".".to_string());
+    Path::new(&home).join(".wayfinder").join("secret_key.bin")
+}
+
+fn load_or_create_secret_fallback_key() -> std::io::Result<[u8; 32]> {
+    let path = secret_fallback_key_path();
+    if let Ok(bytes) = fs::read(&path) {
+        if bytes.len() == 32 {
+            let mut key = [0u8; 32];
+            key.copy_from_slice(&bytes);
+            return Ok(key);
+        }
+    }
+    let mut key = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut key);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, &key)?;
+    Ok(key)
+}
+
+fn secret_hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn secret_hex_decode(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok()).collect()
+}
+
+fn encrypt_secret_fallback(plaintext: &str) -> Result<String, String> {
+    use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+    use aes_gcm::{Aes256Gcm, Key};
+
+    let key_bytes = load_or_create_secret_fallback_key()
+        .map_err(|e| format!("Failed to load secret fallback key: {}", e))?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_bytes));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|e| format!("Failed to encrypt secret: {}", e))?;
+    let mut out = nonce.to_vec();
+    out.extend(ciphertext);
+    Ok(secret_hex_encode(&out))
+}
+
+fn decrypt_secret_fallback(blob: &str) -> Option<String> {
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+    let bytes = secret_hex_decode(blob)?;
+    if bytes.len() < 12 {
+        return None;
+    }
+    let (nonce_bytes, ciphertext) = bytes.split_at(12);
+    let key_bytes = load_or_create_secret_fallback_key().ok()?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_bytes));
+    let plaintext = cipher.decrypt(Nonce::from_slice(nonce_bytes), ciphertext).ok()?;
+    String::from_utf8(plaintext).ok()
+}
+
+fn secret_fallback_file(index_path: &Path, provider: &str, account: &str) -> std::path::PathBuf {
+    index_path.join(format!("{}_{}.secret.enc", provider, account))
+}
+
+/// Persist a secret (Azure `api_key`, GCP service account JSON contents) for
+/// `provider`/`account` under this index: the OS keychain first, an
+/// encrypted file alongside the config if the keychain is unavailable.
+fn store_secret(index_path: &Path, provider: &str, account: &str, secret: &str) -> Result<(), String> {
+    let service = secret_service_name(index_path, provider);
+    if let Ok(entry) = keyring::Entry::new(&service, account) {
+        if entry.set_password(secret).is_ok() {
+            let _ = fs::remove_file(secret_fallback_file(index_path, provider, account));
+            return Ok(());
+        }
+    }
+    let encrypted = encrypt_secret_fallback(secret)?;
+    fs::write(secret_fallback_file(index_path, provider, account), encrypted)
+        .map_err(|e| format!("Failed to write secret fallback file: {}", e))
+}
+
+/// Load a secret previously saved with `store_secret`. Returns an empty
+/// string (same "not configured" shape the rest of this file uses for
+/// missing credentials) if neither the keychain nor the fallback file has it.
+fn load_secret(index_path: &Path, provider: &str, account: &str) -> String {
+    let service = secret_service_name(index_path, provider);
+    if let Ok(entry) = keyring::Entry::new(&service, account) {
+        if let Ok(secret) = entry.get_password() {
+            return secret;
+        }
+    }
+    fs::read_to_string(secret_fallback_file(index_path, provider, account))
+        .ok()
+        .and_then(|blob| decrypt_secret_fallback(blob.trim()))
+        .unwrap_or_default()
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
-pub enum EmbeddingProvider {
+pub enum ProviderKind {
     Local,
     Azure,
     Gcp,
@@ -41,17 +165,24 @@ pub enum EmbeddingProvider {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ProviderConfig {
-    pub provider: EmbeddingProvider,
+    pub provider: ProviderKind,
     #[serde(default)]
     pub local_model: Option<String>,
 }
 
-// Embedding data stored per file
+// Embedding data stored per file (or, for chunked files, per chunk)
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FileEmbedding {
     pub path: String,
     pub embedding: Vec<f32>,        // 1536 dimensions for ada-002
     pub content_hash: String,       // To detect if file changed
+    // Byte range within the source file this embedding covers. Defaults to
+    // 0..0 for entries written before chunking existed, which callers treat
+    // as "whole file" for backward compatibility.
+    #[serde(default)]
+    pub start_byte: usize,
+    #[serde(default)]
+    pub end_byte: usize,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -90,6 +221,14 @@ pub struct FileEntry {
     pub size: u64,
     pub modified: String,
     pub extension: String,
+    // Populated only for recognized image extensions; absent (`None`) for
+    // everything else and for entries scanned before media indexing existed.
+    #[serde(default)]
+    pub image_width: Option<u32>,
+    #[serde(default)]
+    pub image_height: Option<u32>,
+    #[serde(default)]
+    pub blurhash: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -107,6 +246,27 @@ pub struct SearchResult {
     pub preview: Option<String>,
 }
 
+/// One term's occurrence in one document, as stored in `InvertedIndex`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PostingEntry {
+    pub doc_id: usize,
+    pub term_frequency: usize,
+}
+
+/// Persisted BM25 inverted index (`inverted.json`), built once at scan time
+/// (and refreshed incrementally by the file watcher) so `search` scores
+/// queries against postings instead of re-reading every indexed file's
+/// content from disk on each call. `doc_id` indexes into `doc_paths`/
+/// `doc_lengths`, independent of `IndexData.files` ordering.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InvertedIndex {
+    pub postings: HashMap<String, Vec<PostingEntry>>,
+    pub doc_paths: Vec<String>,
+    pub doc_lengths: Vec<usize>,
+    pub avg_doc_length: f32,
+    pub created_at: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct IndexStats {
     pub total_files: usize,
@@ -115,6 +275,57 @@ pub struct IndexStats {
     pub last_updated: String,
 }
 
+/// Structured pre-filter for `search`/`get_timeline`, applied to
+/// `IndexData.files` before any scoring or bucketing happens. `modified_*`
+/// bounds compare against the `YYYY-MM-DD` date portion of `FileEntry.modified`
+/// (same slicing `get_timeline` already uses), so callers can pass a plain
+/// date without matching the full timestamp format.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SearchFilter {
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub modified_after: Option<String>,
+    pub modified_before: Option<String>,
+}
+
+fn modified_date_part(modified: &str) -> &str {
+    if modified.len() >= 10 {
+        &modified[..10]
+    } else {
+        modified
+    }
+}
+
+fn file_matches_filter(file: &FileEntry, filter: &SearchFilter) -> bool {
+    if !filter.extensions.is_empty() && !filter.extensions.iter().any(|e| e.eq_ignore_ascii_case(&file.extension)) {
+        return false;
+    }
+    if let Some(min_size) = filter.min_size {
+        if file.size < min_size {
+            return false;
+        }
+    }
+    if let Some(max_size) = filter.max_size {
+        if file.size > max_size {
+            return false;
+        }
+    }
+    let date_part = modified_date_part(&file.modified);
+    if let Some(after) = &filter.modified_after {
+        if date_part < after.as_str() {
+            return false;
+        }
+    }
+    if let Some(before) = &filter.modified_before {
+        if date_part > before.as_str() {
+            return false;
+        }
+    }
+    true
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct IndexState {
     pub has_files: bool,
@@ -167,6 +378,12 @@ pub struct EmbeddingJobConfig {
     pub max_retries: usize,       // Max retries per file (default: 3)
     pub save_interval: usize,     // Save progress every N files (default: 50)
     pub max_files: Option<usize>, // Limit total files (for testing)
+    #[serde(default = "default_max_tokens_per_request")]
+    pub max_tokens_per_request: usize, // Token budget per batched API call
+}
+
+fn default_max_tokens_per_request() -> usize {
+    8000
 }
 
 impl Default for EmbeddingJobConfig {
@@ -177,14 +394,110 @@ impl Default for EmbeddingJobConfig {
             max_retries: 3,
             save_interval: 50,
             max_files: None,
+            max_tokens_per_request: default_max_tokens_per_request(),
         }
     }
 }
 
+// Approximate token count of a string, used for packing requests within a
+// provider's context limit without pulling in a real tokenizer.
+fn estimate_tokens(text: &str) -> usize {
+    (text.len() / 4).max(1)
+}
+
 fn default_local_model_name() -> String {
     "BAAI/bge-small-en-v1.5".to_string()
 }
 
+/// Read the indexable text for a path, whether it's a plain file or a
+/// synthetic structured-document record (`data.csv#row=42`). Keeps
+/// search/embedding call sites path-agnostic so CSV/TSV/JSONL/NDJSON rows
+/// can be treated just like any other indexed entry.
+fn read_indexed_content(path: &str) -> std::io::Result<String> {
+    if let Some((base_path, row)) = structured_docs::split_synthetic_path(path) {
+        let content = fs::read_to_string(base_path)?;
+        let ext = chunking::extension_of(base_path);
+        return structured_docs::record_text_at(&content, &ext, row)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("Row {} not found in {}", row, base_path)));
+    }
+    let ext = chunking::extension_of(path);
+    if is_image_extension(&ext) {
+        return image_metadata_text(Path::new(path))
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("No readable metadata for image: {}", path)));
+    }
+    fs::read_to_string(path)
+}
+
+/// Image extensions handled by the media pipeline: EXIF/dimensions fold into
+/// searchable content (see `image_metadata_text`), and a BlurHash becomes the
+/// `SearchResult` preview instead of `None` (see `scan_image_info`).
+fn is_image_extension(ext: &str) -> bool {
+    matches!(ext, "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" | "tiff" | "tif")
+}
+
+/// Dimensions and BlurHash preview captured once at scan/reindex time and
+/// stored directly on `FileEntry`, so `get_stats` and `search` never have to
+/// re-decode the image later.
+struct ImageScanInfo {
+    width: u32,
+    height: u32,
+    blurhash: String,
+}
+
+/// Decode an image and compute its pixel dimensions plus a compact BlurHash
+/// string. Returns `None` for files that fail to decode (corrupt or
+/// unsupported image data) so one bad photo doesn't fail the whole scan.
+fn scan_image_info(path: &Path) -> Option<ImageScanInfo> {
+    let img = image::open(path).ok()?;
+    let (width, height) = (img.width(), img.height());
+    // BlurHash encoding cost scales with pixel count; a small thumbnail is
+    // plenty for a legible placeholder blur and keeps large libraries fast
+    // to scan.
+    let thumb = img.thumbnail(32, 32).to_rgba8();
+    let blurhash = blurhash::encode(4, 3, thumb.width(), thumb.height(), thumb.as_raw());
+    Some(ImageScanInfo { width, height, blurhash })
+}
+
+/// Fold an image's EXIF tags (camera, capture date, GPS, dimensions) into a
+/// plain-text blob so `read_indexed_content` can feed it through the same
+/// tokenizer/BM25 path as any other file - this is what makes a photo
+/// library's camera model or date genuinely searchable rather than opaque.
+fn image_metadata_text(path: &Path) -> Option<String> {
+    let mut lines = Vec::new();
+
+    if let Ok((width, height)) = image::image_dimensions(path) {
+        lines.push(format!("dimensions: {}x{}", width, height));
+    }
+
+    if let Ok(file) = fs::File::open(path) {
+        let mut reader = std::io::BufReader::new(file);
+        if let Ok(exif) = exif::Reader::new().read_from_container(&mut reader) {
+            for field in exif.fields() {
+                let interesting = matches!(
+                    field.tag,
+                    exif::Tag::Make
+                        | exif::Tag::Model
+                        | exif::Tag::DateTime
+                        | exif::Tag::DateTimeOriginal
+                        | exif::Tag::GPSLatitude
+                        | exif::Tag::GPSLongitude
+                        | exif::Tag::PixelXDimension
+                        | exif::Tag::PixelYDimension
+                );
+                if interesting {
+                    lines.push(format!("{}: {}", field.tag, field.display_value()));
+                }
+            }
+        }
+    }
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
 fn read_provider_config(index_path: &Path) -> Option<ProviderConfig> {
     let config_file = index_path.join("provider_config.json");
     if !config_file.exists() {
@@ -194,7 +507,7 @@ fn read_provider_config(index_path: &Path) -> Option<ProviderConfig> {
     serde_json::from_str::<ProviderConfig>(&content).ok()
 }
 
-fn write_provider_config(index_path: &Path, provider: EmbeddingProvider, local_model: Option<String>) -> Result<(), String> {
+fn write_provider_config(index_path: &Path, provider: ProviderKind, local_model: Option<String>) -> Result<(), String> {
     let config_file = index_path.join("provider_config.json");
     let config = ProviderConfig {
         provider,
@@ -213,7 +526,7 @@ fn resolve_provider_config(index_path: &Path) -> ProviderConfig {
     }
 
     ProviderConfig {
-        provider: EmbeddingProvider::Local,
+        provider: ProviderKind::Local,
         local_model: Some(default_local_model_name()),
     }
 }
@@ -277,6 +590,7 @@ pub async fn scan_directory(path: String, index_dir: String) -> Result<serde_jso
         "rs", "go", "java", "c", "cpp", "h", "hpp",
         "sh", "bash", "zsh", "ps1", "bat", "cmd",
         "xml", "svg", "log",
+        "csv", "tsv", "jsonl", "ndjson",
     ];
 
     for entry in WalkDir::new(&path)
@@ -301,27 +615,61 @@ pub async fn scan_directory(path: String, index_dir: String) -> Result<serde_jso
                 .unwrap_or("")
                 .to_lowercase();
             
-            // Only index text files
-            if text_extensions.contains(&ext.as_str()) {
+            // Index text files and, for the media pipeline, recognized image
+            // formats (EXIF/dimensions fold into search content, see
+            // `read_indexed_content`; BlurHash becomes the search preview).
+            if text_extensions.contains(&ext.as_str()) || is_image_extension(&ext) {
                 if let Ok(metadata) = fs::metadata(file_path) {
                     let size = metadata.len();
                     total_size += size;
-                    
+
                     let modified = metadata.modified()
                         .ok()
                         .and_then(|t| DateTime::<Local>::from(t).format("%Y-%m-%d %H:%M:%S").to_string().into())
                         .unwrap_or_else(|| "Unknown".to_string());
 
-                    files.push(FileEntry {
-                        path: file_path.to_string_lossy().to_string(),
-                        name: file_path.file_name()
-                            .and_then(|n| n.to_str())
-                            .unwrap_or("unknown")
-                            .to_string(),
-                        size,
-                        modified,
-                        extension: ext,
-                    });
+                    let path_string = file_path.to_string_lossy().to_string();
+                    let name = file_path.file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or("unknown")
+                        .to_string();
+
+                    // CSV/TSV/JSONL/NDJSON are tabular or log-dump data, not
+                    // prose: index each row as its own synthetic-path entry
+                    // (`data.csv#row=42`) instead of one opaque whole-file blob.
+                    if structured_docs::is_structured_extension(&ext) {
+                        if let Ok(content) = fs::read_to_string(file_path) {
+                            for record in structured_docs::parse_records(&content, &ext) {
+                                let record_size = record.text.len() as u64;
+                                files.push(FileEntry {
+                                    path: structured_docs::synthetic_path(&path_string, record.row),
+                                    name: format!("{} (row {})", name, record.row),
+                                    size: record_size,
+                                    modified: modified.clone(),
+                                    extension: ext.clone(),
+                                    image_width: None,
+                                    image_height: None,
+                                    blurhash: None,
+                                });
+                            }
+                        }
+                    } else {
+                        let image_info = if is_image_extension(&ext) {
+                            scan_image_info(file_path)
+                        } else {
+                            None
+                        };
+                        files.push(FileEntry {
+                            path: path_string,
+                            name,
+                            size,
+                            modified,
+                            extension: ext,
+                            image_width: image_info.as_ref().map(|i| i.width),
+                            image_height: image_info.as_ref().map(|i| i.height),
+                            blurhash: image_info.map(|i| i.blurhash),
+                        });
+                    }
                 }
             }
         }
@@ -351,6 +699,10 @@ pub async fn scan_directory(path: String, index_dir: String) -> Result<serde_jso
     fs::write(&index_file, json)
         .map_err(|e| format!("Failed to write index file: {}", e))?;
 
+    if let Err(e) = write_inverted_index(&index_path, &files) {
+        println!("[RUST] Failed to write inverted index: {}", e);
+    }
+
     println!("[RUST] Scan complete - {} files found, {} bytes total", files.len(), total_size);
     println!("[RUST] Index written to: {}", index_file.display());
     
@@ -368,426 +720,796 @@ pub async fn generate_embeddings(index_dir: String, max_files: Option<usize>, ba
     let provider_config = resolve_provider_config(index_path);
 
     match provider_config.provider {
-        EmbeddingProvider::Local => {
-            generate_embeddings_local(index_dir, max_files, provider_config.local_model).await
+        ProviderKind::Local => {
+            generate_embeddings_ollama(index_dir, max_files, batch_size, provider_config.local_model).await
         },
-        EmbeddingProvider::Azure => {
+        ProviderKind::Azure => {
             generate_embeddings_azure(index_dir, max_files, batch_size).await
         },
-        EmbeddingProvider::Gcp => {
+        ProviderKind::Gcp => {
             generate_embeddings_gcp(index_dir, max_files, batch_size).await
         }
     }
 }
 
-// Generate embeddings using a local model
-pub async fn generate_embeddings_local(_index_dir: String, _max_files: Option<usize>, _model_name: Option<String>) -> Result<serde_json::Value, String> {
-    // Simple deterministic local embedding fallback.
-    // This avoids heavyweight native ML crates and provides reproducible vectors for offline use.
-    // It uses a xorshift-style RNG seeded from a stable hash of the file contents.
+const OLLAMA_EMBEDDINGS_URL: &str = "http://localhost:11434/api/embeddings";
+const OLLAMA_TAGS_URL: &str = "http://localhost:11434/api/tags";
 
-    // Helper: compute a stable content hash (hex string)
-    fn content_hash(s: &str) -> String {
-        let mut hasher = DefaultHasher::new();
-        s.hash(&mut hasher);
-        format!("{:016x}", hasher.finish())
+fn normalize_vector(v: &mut [f32]) {
+    let norm: f32 = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 1e-10 {
+        for x in v.iter_mut() {
+            *x /= norm;
+        }
     }
+}
+
+/// Stable content hash (hex string), used both to key embedding dedup/cache
+/// entries and to seed the deterministic fallback embedding below.
+fn content_hash(s: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    s.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+// Deterministic PRNG based on xorshift64*, used only to spread a content hash
+// out into a vector of the right dimensionality.
+fn next_xorshift(state: &mut u64) -> u64 {
+    let mut x = *state;
+    x ^= x >> 12;
+    x ^= x << 25;
+    x ^= x >> 27;
+    *state = x;
+    x.wrapping_mul(2685821657736338717u64)
+}
 
-    // Helper: deterministic PRNG based on xorshift64*
-    fn next_xorshift(state: &mut u64) -> u64 {
-        let mut x = *state;
-        x ^= x >> 12;
-        x ^= x << 25;
-        x ^= x >> 27;
-        *state = x;
-        x.wrapping_mul(2685821657736338717u64)
+/// Produce a `Deterministic`-provider f32 vector of length `dim` from text.
+/// This avoids heavyweight native ML crates and provides reproducible vectors
+/// for offline/CI use, and for embedding ad-hoc query strings against an
+/// index built while Ollama wasn't reachable.
+fn deterministic_embedding(text: &str, dim: usize) -> Vec<f32> {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    let mut state = hasher.finish();
+    // Avoid zero state
+    if state == 0 { state = 0x9E3779B97F4A7C15; }
+    let mut v = Vec::with_capacity(dim);
+    for _ in 0..dim {
+        let r = next_xorshift(&mut state);
+        // convert to f32 in range [-1, 1]
+        let f = (r as f64 / std::u64::MAX as f64) as f32 * 2.0 - 1.0;
+        v.push(f);
     }
+    normalize_vector(&mut v);
+    v
+}
+
+/// Probe a local Ollama server once (short timeout) so callers can decide
+/// up front whether to use it or fall back to `deterministic_embedding`.
+async fn ollama_reachable(client: &reqwest::Client) -> bool {
+    client
+        .get(OLLAMA_TAGS_URL)
+        .timeout(std::time::Duration::from_millis(500))
+        .send()
+        .await
+        .map(|r| r.status().is_success())
+        .unwrap_or(false)
+}
 
-    // Helper: produce an f32 vector of length `dim` from content
-    fn compute_embedding_from_text(text: &str, dim: usize) -> Vec<f32> {
-        let mut hasher = DefaultHasher::new();
-        text.hash(&mut hasher);
-        let mut state = hasher.finish();
-        // Avoid zero state
-        if state == 0 { state = 0x9E3779B97F4A7C15; }
-        let mut v = Vec::with_capacity(dim);
-        for _ in 0..dim {
-            let r = next_xorshift(&mut state);
-            // convert to f32 in range [-1, 1]
-            let f = (r as f64 / std::u64::MAX as f64) as f32 * 2.0 - 1.0;
-            v.push(f);
+/// Embed a single piece of text (e.g. a search query) using Ollama when
+/// reachable, otherwise the deterministic fallback, so a query still gets a
+/// vector to compare against whatever generated the index's embeddings.
+async fn embed_text_local(client: &reqwest::Client, model_name: &str, text: &str, dim: usize) -> Vec<f32> {
+    if ollama_reachable(client).await {
+        match ollama_embed(client, model_name, text).await {
+            Ok(v) => return v,
+            Err(_) => {}
         }
-        v
+    }
+    deterministic_embedding(text, dim)
+}
+
+/// Request an embedding from a local Ollama-compatible server
+/// (`model` e.g. `nomic-embed-text`, driven by `ProviderConfig.local_model`).
+async fn ollama_embed(client: &reqwest::Client, model: &str, text: &str) -> Result<Vec<f32>, String> {
+    let body = serde_json::json!({ "model": model, "prompt": text });
+    let response = client
+        .post(OLLAMA_EMBEDDINGS_URL)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Ollama request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!("Ollama returned {}: {}", status, text));
     }
 
-    // Parameters
-    let dim = 512usize; // local embedding dimension
+    let json: serde_json::Value = response.json().await.map_err(|e| format!("Failed to parse Ollama response: {}", e))?;
+    let embedding = json["embedding"]
+        .as_array()
+        .ok_or_else(|| "Ollama response missing 'embedding' array".to_string())?;
 
-    let index_path = Path::new(&_index_dir);
-    let index_file = index_path.join("index.json");
-    let embeddings_file = index_path.join("embeddings.json");
+    let mut vec: Vec<f32> = embedding.iter().filter_map(|v| v.as_f64().map(|f| f as f32)).collect();
+    normalize_vector(&mut vec);
+    Ok(vec)
+}
 
-    if !index_file.exists() {
-        return Err(format!("Index file not found: {}", index_file.display()));
+/// Error from an embedding provider's backend. `retryable`, when true, tells
+/// the driver loop in `run_provider_embedding_job` to back off and retry
+/// rather than fail the batch outright (e.g. a 429 from Azure/GCP).
+/// `retry_after_ms`, when set, is an explicit wait time the provider read
+/// from a `Retry-After` header; when `None` the driver loop falls back to
+/// its own exponential backoff instead of assuming a fixed delay.
+#[derive(Debug, Clone)]
+pub struct ProviderError {
+    pub message: String,
+    pub retryable: bool,
+    pub retry_after_ms: Option<u64>,
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<ProviderError> for String {
+    fn from(e: ProviderError) -> String {
+        e.message
+    }
+}
+
+impl From<String> for ProviderError {
+    fn from(message: String) -> Self {
+        ProviderError { message, retryable: false, retry_after_ms: None }
     }
+}
 
-    let index_str = fs::read_to_string(&index_file)
-        .map_err(|e| format!("Failed to read index file: {}", e))?;
+// Structured error envelope. Most commands here still return a free-text
+// `String` on failure, which leaves the frontend unable to reliably branch
+// on failure cause (auth failure vs. missing file vs. parse error).
+// `ResponseError` gives those failures a stable, machine-readable `code`
+// alongside the human `message`. Migrated: the config validation commands,
+// the clusters/clippy commands, the file intelligence commands, and the
+// watcher commands - each still returns `Result<serde_json::Value, ResponseError>`
+// with its own existing success shape, so a direct Tauri IPC call is
+// unaffected; `json_result` (the embedded HTTP API's one shared response
+// path) wraps both sides in `Response<T>` so that API gets one uniform
+// envelope instead of each route hand-rolling its own.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResponseError {
+    pub code: String,
+    pub http_status: u16,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
 
-    let index_data: IndexData = serde_json::from_str(&index_str)
-        .map_err(|e| format!("Failed to parse index.json: {}", e))?;
+impl ResponseError {
+    pub fn new(code: &str, message: impl Into<String>) -> Self {
+        ResponseError { code: code.to_string(), http_status: 400, message: message.into(), link: None }
+    }
 
-    // Load existing embeddings (if any)
-    let existing: EmbeddingsData = if embeddings_file.exists() {
-        fs::read_to_string(&embeddings_file)
-            .ok()
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or(EmbeddingsData { embeddings: Vec::new(), model: "local-fallback".to_string(), created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string() })
-    } else {
-        EmbeddingsData { embeddings: Vec::new(), model: "local-fallback".to_string(), created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string() }
-    };
+    pub fn with_status(mut self, http_status: u16) -> Self {
+        self.http_status = http_status;
+        self
+    }
 
-    // Build a map for quick lookup of cached embeddings by path
-    let mut cache_map: HashMap<String, FileEmbedding> = HashMap::new();
-    for fe in existing.embeddings.into_iter() {
-        cache_map.insert(fe.path.clone(), fe);
+    pub fn with_link(mut self, link: impl Into<String>) -> Self {
+        self.link = Some(link.into());
+        self
     }
+}
 
-    let mut generated_count = 0usize;
-    let mut cached_count = 0usize;
-    let mut skipped_count = 0usize;
-    let error_count = 0usize;
+impl std::fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)
+    }
+}
 
-    let max_files = _max_files.unwrap_or(index_data.files.len());
+/// Lets `?` keep working against helpers that still return a plain `String`
+/// error - they surface as a generic `internal_error` rather than losing
+/// the message.
+impl From<String> for ResponseError {
+    fn from(message: String) -> Self {
+        ResponseError { code: "internal_error".to_string(), http_status: 500, message, link: None }
+    }
+}
 
-    let mut out_embeddings: Vec<FileEmbedding> = Vec::new();
+/// Uniform success/failure envelope for commands that don't need a bespoke
+/// success shape.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Response<T> {
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<T>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ResponseError>,
+}
 
-    for (i, entry) in index_data.files.into_iter().enumerate() {
-        if i >= max_files { break; }
-        let path = entry.path.clone();
-        // Try cache
-        if let Some(cached) = cache_map.get(&path) {
-            // Check file still exists and same hash
-            if Path::new(&path).exists() {
-                if let Ok(content) = fs::read_to_string(&path) {
-                    let h = content_hash(&content);
-                    if h == cached.content_hash && cached.embedding.len() == dim {
-                        out_embeddings.push(cached.clone());
-                        cached_count += 1;
-                        continue;
-                    }
+impl<T> Response<T> {
+    pub fn ok(data: T) -> Self {
+        Response { success: true, data: Some(data), error: None }
+    }
+
+    pub fn err(error: ResponseError) -> Self {
+        Response { success: false, data: None, error: Some(error) }
+    }
+}
+
+/// A backend capable of turning text inputs into embedding vectors.
+/// `generate_embeddings_azure`/`generate_embeddings_gcp`/`generate_embeddings_ollama`
+/// each load and validate their own config, build one of these, and hand it
+/// to `run_provider_embedding_job`, which owns the dedup/batching/retry/save
+/// loop shared across providers.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Embed `inputs` and return one vector per input, in the same order.
+    async fn embed_batch(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, ProviderError>;
+    /// Upper bound on estimated tokens (see `estimate_tokens`) to pack into
+    /// a single `embed_batch` call.
+    fn max_batch_tokens(&self) -> usize;
+    /// Model identifier recorded in `embeddings.json`.
+    fn model_name(&self) -> String;
+}
+
+/// `EmbeddingProvider` backed by a local Ollama server. Unlike
+/// `embed_text_local`'s deterministic fallback (used for query-time
+/// embedding), this provider is strict: a missing/unreachable server is a
+/// hard error rather than a silent downgrade, since indexing with it
+/// explicitly asked for Ollama.
+pub struct OllamaProvider {
+    client: reqwest::Client,
+    model: String,
+}
+
+impl OllamaProvider {
+    pub fn new(model: String) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(60))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        Ok(Self { client, model })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaProvider {
+    async fn embed_batch(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let mut out = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let vector = ollama_embed(&self.client, &self.model, input)
+                .await
+                .map_err(|message| ProviderError { message, retryable: false, retry_after_ms: None })?;
+            out.push(vector);
+        }
+        Ok(out)
+    }
+
+    fn max_batch_tokens(&self) -> usize {
+        // Ollama's /api/embeddings takes one prompt per HTTP call, so this
+        // only bounds how many files land in one driver-level progress batch.
+        2000
+    }
+
+    fn model_name(&self) -> String {
+        format!("ollama:{}", self.model)
+    }
+}
+
+/// `EmbeddingProvider` backed by Azure OpenAI's embeddings endpoint. Holds
+/// `api_version` behind a mutex so a single "API version not supported"
+/// response can fall back to `2023-10-01` and retry internally, without
+/// leaking that Azure-specific quirk into the generic driver.
+pub struct AzureOpenAiProvider {
+    client: reqwest::Client,
+    config: AzureConfig,
+    api_version: std::sync::Mutex<String>,
+}
+
+impl AzureOpenAiProvider {
+    pub fn new(config: AzureConfig) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(60))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        let api_version = if config.api_version.is_empty() {
+            "2024-02-01".to_string()
+        } else {
+            config.api_version.clone()
+        };
+        Ok(Self { client, config, api_version: std::sync::Mutex::new(api_version) })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for AzureOpenAiProvider {
+    async fn embed_batch(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let mut attempted_fallback = false;
+        loop {
+            let api_version = self.api_version.lock().map(|v| v.clone()).unwrap_or_else(|_| "2024-02-01".to_string());
+            let mut base = self.config.endpoint.trim_end_matches('/').to_string();
+            if !base.ends_with("/openai") && !base.ends_with("/openai/") {
+                base = format!("{}/openai", base);
+            }
+            let url = format!("{}/deployments/{}/embeddings?api-version={}", base, self.config.deployment_name, api_version);
+
+            let response = self
+                .client
+                .post(&url)
+                .header("api-key", &self.config.api_key)
+                .header("Content-Type", "application/json")
+                .json(&serde_json::json!({ "input": inputs }))
+                .send()
+                .await
+                .map_err(|e| ProviderError { message: e.to_string(), retryable: false, retry_after_ms: None })?;
+
+            if response.status().is_success() {
+                let json: serde_json::Value = response
+                    .json()
+                    .await
+                    .map_err(|e| ProviderError { message: format!("Failed to parse Azure response: {}", e), retryable: false, retry_after_ms: None })?;
+                if let Some(err) = json.get("error") {
+                    return Err(ProviderError { message: err.to_string(), retryable: false, retry_after_ms: None });
                 }
+                let data = json["data"].as_array().cloned().unwrap_or_default();
+                return Ok(data
+                    .iter()
+                    .map(|entry| {
+                        entry["embedding"]
+                            .as_array()
+                            .map(|arr| arr.iter().filter_map(|v| v.as_f64().map(|f| f as f32)).collect())
+                            .unwrap_or_default()
+                    })
+                    .collect());
             }
-        }
 
-        // Read file and compute embedding
-        match fs::read_to_string(&path) {
-            Ok(content) => {
-                let emb = compute_embedding_from_text(&content, dim);
-                let ch = content_hash(&content);
-                out_embeddings.push(FileEmbedding { path: path.clone(), embedding: emb, content_hash: ch });
-                generated_count += 1;
-            },
-            Err(e) => {
-                log_error(index_path, "generate_embeddings_local", Some(&path), &format!("Failed to read file: {}", e), None);
-                skipped_count += 1;
+            if response.status().as_u16() == 429 || response.status().is_server_error() {
+                // Honor an explicit Retry-After when Azure sends one; when it
+                // doesn't, leave retry_after_ms unset so the driver loop
+                // applies its own exponential backoff instead of a fixed wait.
+                let retry_after_ms = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|h| h.to_str().ok())
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .map(|secs| secs.min(60) * 1000);
+                return Err(ProviderError {
+                    message: format!("Azure returned {}", response.status()),
+                    retryable: true,
+                    retry_after_ms,
+                });
             }
-        }
 
-        // Save intermittently every 100 files
-        if (generated_count + cached_count) % 100 == 0 {
-            let save_data = EmbeddingsData { embeddings: out_embeddings.clone(), model: "local-fallback".to_string(), created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string() };
-            if let Ok(json) = serde_json::to_string_pretty(&save_data) {
-                let _ = fs::write(&embeddings_file, json);
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            if !attempted_fallback && error_text.contains("API version not supported") {
+                if let Ok(mut v) = self.api_version.lock() {
+                    *v = "2023-10-01".to_string();
+                }
+                attempted_fallback = true;
+                continue;
             }
+            return Err(ProviderError { message: format!("{}: {}", status, error_text), retryable: false, retry_after_ms: None });
         }
     }
 
-    // Final save
-    let save_data = EmbeddingsData { embeddings: out_embeddings.clone(), model: "local-fallback".to_string(), created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string() };
-    let json = serde_json::to_string_pretty(&save_data).map_err(|e| format!("Failed to serialize embeddings: {}", e))?;
-    fs::write(&embeddings_file, json).map_err(|e| format!("Failed to write embeddings file: {}", e))?;
+    fn max_batch_tokens(&self) -> usize {
+        default_max_tokens_per_request()
+    }
+
+    fn model_name(&self) -> String {
+        self.config.deployment_name.clone()
+    }
+}
 
-    println!("[RUST] Local embeddings complete: {} generated, {} cached, {} skipped, {} errors", generated_count, cached_count, skipped_count, error_count);
+/// `EmbeddingProvider` backed by Google Cloud Vertex AI's `:predict`
+/// endpoint. Unlike the legacy one-file-per-request `generate_embeddings_gcp`
+/// loop this replaces, Vertex's text-embedding models accept multiple
+/// `instances` per call, so this genuinely batches.
+pub struct GcpVertexProvider {
+    client: reqwest::Client,
+    url: String,
+    bearer: String,
+    model_id: String,
+}
 
-    Ok(serde_json::json!({
-        "embeddings_generated": generated_count,
-        "embeddings_cached": cached_count,
-        "embeddings_skipped": skipped_count,
-        "embeddings_errors": error_count,
-        "embeddings_path": embeddings_file.to_string_lossy().to_string(),
-    }))
+#[async_trait]
+impl EmbeddingProvider for GcpVertexProvider {
+    async fn embed_batch(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, ProviderError> {
+        let instances: Vec<serde_json::Value> = inputs.iter().map(|c| serde_json::json!({ "content": c })).collect();
+        let response = self
+            .client
+            .post(&self.url)
+            .bearer_auth(&self.bearer)
+            .json(&serde_json::json!({ "instances": instances }))
+            .send()
+            .await
+            .map_err(|e| ProviderError { message: format!("GCP request failed: {}", e), retryable: false, retry_after_ms: None })?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let retryable = status.as_u16() == 429 || status.is_server_error();
+            // Honor an explicit Retry-After when Vertex sends one; when it
+            // doesn't, leave retry_after_ms unset so the driver loop applies
+            // its own exponential backoff instead of a fixed wait.
+            let retry_after_ms = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|s| s.parse::<u64>().ok())
+                .map(|secs| secs.min(60) * 1000);
+            let text = response.text().await.unwrap_or_default();
+            return Err(ProviderError { message: format!("{}: {}", status, text), retryable, retry_after_ms });
+        }
+
+        let json: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| ProviderError { message: format!("Failed to parse GCP response: {}", e), retryable: false, retry_after_ms: None })?;
+        let predictions = json["predictions"].as_array().cloned().unwrap_or_default();
+        Ok(predictions
+            .iter()
+            .map(|p| {
+                p["embedding"]["values"]
+                    .as_array()
+                    .map(|arr| arr.iter().filter_map(|v| v.as_f64().map(|f| f as f32)).collect())
+                    .unwrap_or_default()
+            })
+            .collect())
+    }
+
+    fn max_batch_tokens(&self) -> usize {
+        default_max_tokens_per_request()
+    }
+
+    fn model_name(&self) -> String {
+        self.model_id.clone()
+    }
 }
 
-/// Generate embeddings using Azure OpenAI with auto-batching and progress saving
-pub async fn generate_embeddings_azure(index_dir: String, max_files: Option<usize>, batch_size: Option<usize>) -> Result<serde_json::Value, String> {
-    println!("[RUST] generate_embeddings_azure called for: {}", index_dir);
-    let index_path = Path::new(&index_dir);
+/// Shared embedding-job loop used by every `EmbeddingProvider`: chunks each
+/// file into token-bounded, range-scoped segments (carrying `start_byte`/
+/// `end_byte` onto each `FileEmbedding`), dedups by per-chunk content hash,
+/// packs requests by token budget, writes resumable batch files under
+/// `embedding_batches/`, retries with backoff driven by
+/// `ProviderError::retry_after_ms`, and atomically merges the result into
+/// `embeddings.json`. Adding a new remote backend means writing an
+/// `EmbeddingProvider` impl, not another copy of this loop.
+async fn run_provider_embedding_job(
+    index_dir: &str,
+    provider: &dyn EmbeddingProvider,
+    max_files: Option<usize>,
+    batch_size: Option<usize>,
+) -> Result<serde_json::Value, String> {
+    let index_path = Path::new(index_dir);
     let index_file = index_path.join("index.json");
-    let config_file = index_path.join("azure_config.json");
     let embeddings_file = index_path.join("embeddings.json");
     let progress_file = index_path.join("embedding_progress.json");
     let batch_dir = index_path.join("embedding_batches");
+    let cache_file = index_path.join("embeddings_cache.json");
     let _ = fs::create_dir_all(&batch_dir);
 
-    // Configuration
+    // Persistent cache keyed by content_hash (not path), so content that's
+    // identical to something embedded in a previous run - even a different
+    // scan, a renamed file, or a re-scan after edits round-tripped back to
+    // the same text - is never re-sent to the provider.
+    let mut embedding_cache: HashMap<String, Vec<f32>> = if cache_file.exists() {
+        fs::read_to_string(&cache_file)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
+    let mut cache_hits = 0usize;
+
     let config_batch_size = batch_size.unwrap_or(100);
+    let max_tokens_per_request = provider.max_batch_tokens();
 
-    // Check if index exists
     if !index_file.exists() {
         return Err("Index not found. Please scan a directory first.".to_string());
     }
-    // Load Azure config
-    if !config_file.exists() {
-        return Err("Azure config not found. Please configure Azure OpenAI settings first.".to_string());
-    }
-    let config_content = fs::read_to_string(&config_file)
-        .map_err(|e| format!("Failed to read Azure config: {}", e))?;
-    let config: AzureConfig = serde_json::from_str(&config_content)
-        .map_err(|e| format!("Failed to parse Azure config: {}", e))?;
-    if config.endpoint.is_empty() || config.api_key.is_empty() || config.deployment_name.is_empty() {
-        return Err("Azure config is incomplete. Please set endpoint, API key, and deployment name.".to_string());
-    }
-    // Load index
-    let index_content = fs::read_to_string(&index_file)
-        .map_err(|e| format!("Failed to read index: {}", e))?;
-    let index_data: IndexData = serde_json::from_str(&index_content)
-        .map_err(|e| format!("Failed to parse index: {}", e))?;
-    // Apply max_files limit if specified
+    let index_content = fs::read_to_string(&index_file).map_err(|e| format!("Failed to read index: {}", e))?;
+    let index_data: IndexData = serde_json::from_str(&index_content).map_err(|e| format!("Failed to parse index: {}", e))?;
     let files_to_process: Vec<FileEntry> = if let Some(max) = max_files {
         index_data.files.into_iter().take(max).collect()
     } else {
         index_data.files
     };
     let total_files = files_to_process.len();
-    let total_batches = (total_files + config_batch_size - 1) / config_batch_size;
-    println!("[RUST] Processing {} files in {} batches of {}", total_files, total_batches, config_batch_size);
 
-    // Load existing batch files for resuming
+    // Load existing batch files (and any legacy embeddings.json) for resuming.
     let mut processed_paths = std::collections::HashSet::new();
-    let mut new_embeddings: Vec<FileEmbedding> = Vec::new();
+    let mut all_embeddings: Vec<FileEmbedding> = Vec::new();
     let mut batch_idx = 0;
     if let Ok(read_dir) = fs::read_dir(&batch_dir) {
-        for entry in read_dir {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.extension().map(|e| e == "json").unwrap_or(false) {
-                    if let Ok(content) = fs::read_to_string(&path) {
-                        if let Ok(data) = serde_json::from_str::<EmbeddingsData>(&content) {
-                            for emb in data.embeddings {
-                                processed_paths.insert(emb.path.clone());
-                                new_embeddings.push(emb);
-                            }
+        for entry in read_dir.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.extension().map(|e| e == "json").unwrap_or(false) {
+                if let Ok(content) = fs::read_to_string(&path) {
+                    if let Ok(data) = serde_json::from_str::<EmbeddingsData>(&content) {
+                        for emb in data.embeddings {
+                            processed_paths.insert(emb.path.clone());
+                            all_embeddings.push(emb);
                         }
                     }
                 }
+                batch_idx += 1;
             }
         }
     }
-    // Also load from main embeddings.json if present (legacy)
     if embeddings_file.exists() {
         if let Ok(content) = fs::read_to_string(&embeddings_file) {
             if let Ok(data) = serde_json::from_str::<EmbeddingsData>(&content) {
                 for emb in data.embeddings {
-                    processed_paths.insert(emb.path.clone());
-                    new_embeddings.push(emb);
+                    if processed_paths.insert(emb.path.clone()) {
+                        all_embeddings.push(emb);
+                    }
                 }
             }
         }
     }
     let cached_count = processed_paths.len();
-    let mut generated_count = 0;
-    let mut error_count = 0;
-    let mut skipped_count = 0;
-    let mut api_version = if config.api_version.is_empty() {
-        "2024-02-01".to_string()
-    } else {
-        config.api_version.clone()
-    };
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-    let mut progress = BatchProgress {
-        batch_id: format!("{}", Local::now().timestamp()),
-        total_files,
-        processed_files: processed_paths.len(),
-        current_batch: 0,
-        total_batches,
-        batch_size: config_batch_size,
+    let mut generated_count = 0usize;
+    let mut error_count = 0usize;
+    let mut skipped_count = 0usize;
+
+    // Chunk each file into token-bounded, range-scoped segments rather than
+    // sending whole files as one oversized, silently-truncated input.
+    struct QueueItem<'a> {
+        file: &'a FileEntry,
+        start_byte: usize,
+        end_byte: usize,
+        content_hash: String,
+        input: String,
+    }
+    let mut queue: Vec<QueueItem> = Vec::new();
+    for file in files_to_process.iter().filter(|f| !processed_paths.contains(&f.path)) {
+        let content = match read_indexed_content(&file.path) {
+            Ok(c) => c,
+            Err(e) => {
+                skipped_count += 1;
+                log_error(index_path, "read_file", Some(&file.path), &e.to_string(), None);
+                continue;
+            }
+        };
+        if content.trim().is_empty() {
+            skipped_count += 1;
+            continue;
+        }
+        let extension = chunking::extension_of(&file.path);
+        for chunk in chunking::chunk_text(&content, &extension) {
+            let ch = content_hash(&chunk.text);
+            if let Some(vector) = embedding_cache.get(&ch) {
+                all_embeddings.push(FileEmbedding {
+                    path: file.path.clone(),
+                    embedding: vector.clone(),
+                    content_hash: ch,
+                    start_byte: chunk.start_byte,
+                    end_byte: chunk.end_byte,
+                });
+                cache_hits += 1;
+                continue;
+            }
+            queue.push(QueueItem {
+                file,
+                start_byte: chunk.start_byte,
+                end_byte: chunk.end_byte,
+                content_hash: ch,
+                input: format!("passage: {}", chunk.text),
+            });
+        }
+    }
+
+    // Dedup layer: identical content only needs one representative sent to
+    // the provider; its vector is fanned back out to every sharing path.
+    let mut hash_to_indices: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, item) in queue.iter().enumerate() {
+        hash_to_indices.entry(item.content_hash.clone()).or_default().push(i);
+    }
+    let representative_indices: Vec<usize> = hash_to_indices.values().map(|idxs| idxs[0]).collect();
+
+    let mut batches: Vec<Vec<usize>> = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+    let mut current_tokens = 0usize;
+    for &i in &representative_indices {
+        let item_tokens = estimate_tokens(&queue[i].input);
+        let would_overflow = !current.is_empty()
+            && (current_tokens + item_tokens > max_tokens_per_request || current.len() >= config_batch_size);
+        if would_overflow {
+            batches.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        current.push(i);
+        current_tokens += item_tokens;
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    let total_batches = batches.len();
+    println!(
+        "[RUST] {}: processing {} chunks in {} token-budgeted batches (max {} tokens/request)",
+        provider.model_name(), queue.len(), total_batches, max_tokens_per_request
+    );
+
+    let mut progress = BatchProgress {
+        batch_id: format!("{}", Local::now().timestamp()),
+        total_files,
+        processed_files: processed_paths.len(),
+        current_batch: 0,
+        total_batches,
+        batch_size: config_batch_size,
         status: "running".to_string(),
         started_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
         last_updated: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
         errors: Vec::new(),
     };
     let _ = fs::write(&progress_file, serde_json::to_string_pretty(&progress).unwrap_or_default());
-    // Process in batches
-    for batch_start in (0..total_files).step_by(config_batch_size) {
-        let batch_end = (batch_start + config_batch_size).min(total_files);
-        let batch_files: Vec<_> = files_to_process[batch_start..batch_end].iter().filter(|f| !processed_paths.contains(&f.path)).collect();
-        if batch_files.is_empty() {
-            batch_idx += 1;
-            continue;
-        }
+
+    for batch_item_indices in &batches {
+        let batch_items: Vec<&QueueItem> = batch_item_indices.iter().map(|&i| &queue[i]).collect();
+        let inputs: Vec<String> = batch_items.iter().map(|it| it.input.clone()).collect();
+
+        let mut retries = 0;
+        let max_retries = 5;
+        let max_total_wait_ms: u64 = 5 * 60 * 1000;
+        let mut total_wait_ms: u64 = 0;
         let mut batch_embeddings: Vec<FileEmbedding> = Vec::new();
-        for file in batch_files.iter() {
-            let content = match fs::read_to_string(&file.path) {
-                Ok(c) => c,
-                Err(e) => {
-                    skipped_count += 1;
-                    log_error(&index_path, "read_file", Some(&file.path), &e.to_string(), None);
-                    continue;
-                }
-            };
-            if content.trim().is_empty() {
-                skipped_count += 1;
-                continue;
-            }
-            let content_hash = format!("{:x}", md5_hash(&content));
-            let truncated_content = if content.len() > 32000 {
-                content[..32000].to_string()
-            } else {
-                content.clone()
-            };
-            let input = format!("passage: {}", truncated_content);
-            let mut retries = 0;
-            let max_retries = 3;
-            let mut success = false;
-            while retries < max_retries && !success {
-                let mut base = config.endpoint.trim_end_matches('/').to_string();
-                if !base.ends_with("/openai") && !base.ends_with("/openai/") {
-                    base = format!("{}/openai", base);
-                }
-                let url_current = format!("{}/deployments/{}/embeddings?api-version={}", base, config.deployment_name, api_version);
-                let request_body = serde_json::json!({ "input": input });
-                match client
-                    .post(&url_current)
-                    .header("api-key", &config.api_key)
-                    .header("Content-Type", "application/json")
-                    .json(&request_body)
-                    .send()
-                    .await
-                {
-                    Ok(response) => {
-                        if response.status().is_success() {
-                            match response.json::<serde_json::Value>().await {
-                                Ok(json) => {
-                                    // Check for explicit error field
-                                    if json.get("error").is_some() {
-                                        let err_text = json["error"].to_string();
-                                        log_error(&index_path, "api_error", Some(&file.path), &err_text, None);
-                                        progress.errors.push(format!("{}: API error - {}", file.name, err_text));
-                                        error_count += 1;
-                                    } else if let Some(embedding) = json["data"][0]["embedding"].as_array() {
-                                        let emb_vec: Vec<f32> = embedding
-                                            .iter()
-                                            .filter_map(|v| v.as_f64().map(|f| f as f32))
-                                            .collect();
-                                        batch_embeddings.push(FileEmbedding {
-                                            path: file.path.clone(),
-                                            embedding: emb_vec,
-                                            content_hash: content_hash.clone(),
-                                        });
-                                        generated_count += 1;
-                                        success = true;
-                                    } else {
-                                        // Unexpected response shape
-                                        let err_text = json.to_string();
-                                        log_error(&index_path, "api_error", Some(&file.path), &format!("Unexpected response: {}", err_text), None);
-                                        progress.errors.push(format!("{}: Unexpected response shape", file.name));
-                                        error_count += 1;
-                                    }
-                                }
-                                Err(e) => {
-                                    log_error(&index_path, "parse_error", Some(&file.path), &format!("Failed to parse JSON: {}", e), None);
-                                    progress.errors.push(format!("{}: Failed to parse JSON", file.name));
-                                    error_count += 1;
-                                }
-                            }
-                        } else if response.status().as_u16() == 429 {
-                            // Rate limited - wait and retry
-                            let wait_time = 2u64.pow(retries as u32) * 1000;
-                            println!("[RUST] Rate limited, waiting {}ms...", wait_time);
-                            log_error(&index_path, "rate_limit", Some(&file.path), "Rate limited by Azure", Some("429"));
-                            tokio::time::sleep(tokio::time::Duration::from_millis(wait_time)).await;
-                            retries += 1;
-                        } else {
-                            let status = response.status();
-                            let error_text = response.text().await.unwrap_or_default();
-                            // Detect unsupported API version and attempt a fallback once
-                            if error_text.contains("API version not supported") {
-                                if api_version != "2023-10-01" {
-                                    println!("[RUST] API version not supported, attempting fallback to 2023-10-01");
-                                    api_version = "2023-10-01".to_string();
-                                    retries = 0;
-                                    continue; // retry this request with new api_version
-                                }
-                            }
-                            log_error(&index_path, "api_error", Some(&file.path), &error_text, Some(&status.to_string()));
+
+        loop {
+            match provider.embed_batch(&inputs).await {
+                Ok(vectors) => {
+                    for (item, vector) in batch_items.iter().zip(vectors.iter()) {
+                        if vector.is_empty() {
+                            log_error(index_path, "api_error", Some(&item.file.path), "Missing embedding in response", None);
+                            progress.errors.push(format!("{}: missing embedding in response", item.file.name));
                             error_count += 1;
-                            progress.errors.push(format!("{}: {} - {}", file.name, status, error_text));
-                            break;
+                            continue;
                         }
-                    }
-                    Err(e) => {
-                        if retries < max_retries - 1 {
-                            let wait_time = 2u64.pow(retries as u32) * 500;
-                            tokio::time::sleep(tokio::time::Duration::from_millis(wait_time)).await;
-                            retries += 1;
-                        } else {
-                            log_error(&index_path, "request_error", Some(&file.path), &e.to_string(), None);
-                            error_count += 1;
-                            progress.errors.push(format!("{}: {}", file.name, e));
-                            break;
+                        embedding_cache.insert(item.content_hash.clone(), vector.clone());
+                        for &sibling_idx in &hash_to_indices[&item.content_hash] {
+                            let sibling = &queue[sibling_idx];
+                            batch_embeddings.push(FileEmbedding {
+                                path: sibling.file.path.clone(),
+                                embedding: vector.clone(),
+                                content_hash: sibling.content_hash.clone(),
+                                start_byte: sibling.start_byte,
+                                end_byte: sibling.end_byte,
+                            });
                         }
+                        generated_count += hash_to_indices[&item.content_hash].len();
                     }
+                    break;
+                }
+                Err(e) => {
+                    if total_wait_ms < max_total_wait_ms && e.retryable && retries < max_retries {
+                        // Honor an explicit Retry-After from the provider; otherwise
+                        // fall back to exponential backoff (base 1s, doubling, capped).
+                        let backoff_cap_ms: u64 = 60_000;
+                        let wait_ms = e
+                            .retry_after_ms
+                            .unwrap_or_else(|| (1000u64.saturating_mul(1 << retries.min(10))).min(backoff_cap_ms));
+                        let jitter_ms = rand::thread_rng().gen_range(0..250);
+                        println!("[RUST] {} rate limited, waiting {}ms before retrying batch: {}", provider.model_name(), wait_ms, e.message);
+                        log_error(index_path, "rate_limit", None, &e.message, None);
+                        tokio::time::sleep(tokio::time::Duration::from_millis(wait_ms + jitter_ms)).await;
+                        total_wait_ms += wait_ms + jitter_ms;
+                        retries += 1;
+                        continue;
+                    }
+                    log_error(index_path, "provider_error", None, &e.message, None);
+                    progress.errors.push(format!("batch: {}", e.message));
+                    error_count += batch_items.len();
+                    break;
                 }
             }
         }
-        // Save this batch
+
+        // Save this batch atomically: write to a temp file then rename into
+        // place, so a crash mid-flush never leaves a half-written batch file.
         let batch_data = EmbeddingsData {
             embeddings: batch_embeddings.clone(),
-            model: config.deployment_name.clone(),
+            model: provider.model_name(),
             created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
         };
         let batch_file = batch_dir.join(format!("embeddings_part_{:03}.json", batch_idx));
-        let _ = fs::write(&batch_file, serde_json::to_string_pretty(&batch_data).unwrap_or_default());
-        // Add to global
-        new_embeddings.extend(batch_embeddings);
+        let tmp_file = batch_dir.join(format!("embeddings_part_{:03}.json.tmp", batch_idx));
+        if let Ok(json) = serde_json::to_string_pretty(&batch_data) {
+            if fs::write(&tmp_file, json).is_ok() {
+                let _ = fs::rename(&tmp_file, &batch_file);
+            }
+        }
+        all_embeddings.extend(batch_embeddings);
         batch_idx += 1;
-        // Save progress
-        progress.processed_files = new_embeddings.len();
+
+        // Flush the content-hash cache alongside the batch, so the two stay
+        // consistent if the job is interrupted right after this point.
+        let cache_tmp = index_path.join("embeddings_cache.json.tmp");
+        if let Ok(json) = serde_json::to_string_pretty(&embedding_cache) {
+            if fs::write(&cache_tmp, json).is_ok() {
+                let _ = fs::rename(&cache_tmp, &cache_file);
+            }
+        }
+
+        progress.processed_files = all_embeddings.len();
         progress.current_batch = batch_idx;
         progress.last_updated = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
         let _ = fs::write(&progress_file, serde_json::to_string_pretty(&progress).unwrap_or_default());
     }
-    // Final save: merge all batches into embeddings.json
+
     let final_data = EmbeddingsData {
-        embeddings: new_embeddings.clone(),
-        model: config.deployment_name.clone(),
+        embeddings: all_embeddings.clone(),
+        model: provider.model_name(),
         created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
     };
     let json = serde_json::to_string_pretty(&final_data).map_err(|e| format!("Failed to serialize embeddings: {}", e))?;
-    fs::write(&embeddings_file, json).map_err(|e| format!("Failed to write embeddings file: {}", e))?;
-    // Optionally, clean up batch files here if desired
-    println!("[RUST] Embeddings complete: {} generated, {} cached, {} skipped, {} errors", generated_count, cached_count, skipped_count, error_count);
+    let embeddings_tmp = index_path.join("embeddings.json.tmp");
+    fs::write(&embeddings_tmp, json).map_err(|e| format!("Failed to write embeddings file: {}", e))?;
+    fs::rename(&embeddings_tmp, &embeddings_file).map_err(|e| format!("Failed to write embeddings file: {}", e))?;
+
+    progress.status = "complete".to_string();
+    let _ = fs::write(&progress_file, serde_json::to_string_pretty(&progress).unwrap_or_default());
+
+    println!(
+        "[RUST] {} embeddings complete: {} generated, {} resumed, {} from content cache, {} skipped, {} errors",
+        provider.model_name(), generated_count, cached_count, cache_hits, skipped_count, error_count
+    );
     Ok(serde_json::json!({
         "embeddings_generated": generated_count,
         "cached_count": cached_count,
+        "content_cache_hits": cache_hits,
         "skipped_count": skipped_count,
         "error_count": error_count,
-        "total_files": new_embeddings.len(),
-        "message": format!("Generated {} new embeddings, {} from cache, {} skipped, {} errors", generated_count, cached_count, skipped_count, error_count)
+        "total_files": all_embeddings.len(),
+        "message": format!("Generated {} new embeddings, {} resumed, {} from content cache, {} skipped, {} errors", generated_count, cached_count, cache_hits, skipped_count, error_count)
     }))
 }
 
+/// Generate embeddings using Azure OpenAI with auto-batching and progress saving
+pub async fn generate_embeddings_azure(index_dir: String, max_files: Option<usize>, batch_size: Option<usize>) -> Result<serde_json::Value, String> {
+    println!("[RUST] generate_embeddings_azure called for: {}", index_dir);
+    let provider = build_azure_provider(Path::new(&index_dir))?;
+    run_provider_embedding_job(&index_dir, &provider, max_files, batch_size).await
+}
+
+/// Load `azure_config.json` and build the `AzureOpenAiProvider` it describes.
+/// Shared by `generate_embeddings_azure` and `dual_retrieve` (query-time
+/// embedding) so both embed into the same vector space as the configured
+/// Azure deployment.
+fn build_azure_provider(index_path: &Path) -> Result<AzureOpenAiProvider, String> {
+    let config_file = index_path.join("azure_config.json");
+    if !config_file.exists() {
+        return Err("Azure config not found. Please configure Azure OpenAI settings first.".to_string());
+    }
+    let config_content = fs::read_to_string(&config_file)
+        .map_err(|e| format!("Failed to read Azure config: {}", e))?;
+    let mut config: AzureConfig = serde_json::from_str(&config_content)
+        .map_err(|e| format!("Failed to parse Azure config: {}", e))?;
+    if config.api_key.is_empty() {
+        config.api_key = load_secret(index_path, "azure", "api_key");
+    }
+    if config.endpoint.is_empty() || config.api_key.is_empty() || config.deployment_name.is_empty() {
+        return Err("Azure config is incomplete. Please set endpoint, API key, and deployment name.".to_string());
+    }
+    AzureOpenAiProvider::new(config)
+}
+
 /// Generate embeddings using Google Cloud Vertex AI
-pub async fn generate_embeddings_gcp(index_dir: String, max_files: Option<usize>, _batch_size: Option<usize>) -> Result<serde_json::Value, String> {
+pub async fn generate_embeddings_gcp(index_dir: String, max_files: Option<usize>, batch_size: Option<usize>) -> Result<serde_json::Value, String> {
     println!("[RUST] generate_embeddings_gcp called for: {}", index_dir);
-    let index_path = Path::new(&index_dir);
-    let index_file = index_path.join("index.json");
+    let provider = build_gcp_provider(Path::new(&index_dir)).await?;
+    run_provider_embedding_job(&index_dir, &provider, max_files, batch_size).await
+}
+
+/// Load `gcp_config.json`, mint a service-account bearer token, and build the
+/// `GcpVertexProvider` it describes. Shared by `generate_embeddings_gcp` and
+/// `dual_retrieve` (query-time embedding) so both embed into the same vector
+/// space as the configured Vertex model.
+async fn build_gcp_provider(index_path: &Path) -> Result<GcpVertexProvider, String> {
     let config_file = index_path.join("gcp_config.json");
-    let embeddings_file = index_path.join("embeddings.json");
 
     if !config_file.exists() {
         return Err("GCP config not found. Please configure GCP settings first.".to_string());
@@ -795,32 +1517,26 @@ pub async fn generate_embeddings_gcp(index_dir: String, max_files: Option<usize>
     let config_content = fs::read_to_string(&config_file).map_err(|e| format!("Failed to read GCP config: {}", e))?;
     let config: GcpConfig = serde_json::from_str(&config_content).map_err(|e| format!("Failed to parse GCP config: {}", e))?;
 
-    if !index_file.exists() {
-        return Err("Index not found. Please scan a directory first.".to_string());
-    }
-    let index_content = fs::read_to_string(&index_file).map_err(|e| format!("Failed to read index: {}", e))?;
-    let index_data: IndexData = serde_json::from_str(&index_content).map_err(|e| format!("Failed to parse index: {}", e))?;
-
-    let files_to_process: Vec<FileEntry> = if let Some(max) = max_files {
-        index_data.files.into_iter().take(max).collect()
+    // Prefer the service account JSON stashed in the keychain by
+    // `save_gcp_config` over re-reading the original file, so the index
+    // folder stays usable even if that file is later moved or deleted.
+    let stored_json = load_secret(index_path, "gcp", "service_account");
+    let scopes = &["https://www.googleapis.com/auth/cloud-platform"];
+    let key = if !stored_json.trim().is_empty() {
+        yup_oauth2::parse_service_account_key(&stored_json)
+            .map_err(|e| format!("Failed to parse stored service account key: {}", e))?
     } else {
-        index_data.files
+        if config.service_account_path.trim().is_empty() {
+            return Err("GCP service account JSON path is required".to_string());
+        }
+        let sa_path = Path::new(&config.service_account_path);
+        if !sa_path.exists() {
+            return Err(format!("Service account file not found: {}", config.service_account_path));
+        }
+        yup_oauth2::read_service_account_key(sa_path)
+            .await
+            .map_err(|e| format!("Failed to read service account key: {}", e))?
     };
-
-    if config.service_account_path.trim().is_empty() {
-        return Err("GCP service account JSON path is required".to_string());
-    }
-
-    let sa_path = Path::new(&config.service_account_path);
-    if !sa_path.exists() {
-        return Err(format!("Service account file not found: {}", config.service_account_path));
-    }
-
-    // Build an access token using the service account
-    let scopes = &["https://www.googleapis.com/auth/cloud-platform"];
-    let key = yup_oauth2::read_service_account_key(sa_path)
-        .await
-        .map_err(|e| format!("Failed to read service account key: {}", e))?;
     let auth = yup_oauth2::ServiceAccountAuthenticator::builder(key)
         .build()
         .await
@@ -834,68 +1550,41 @@ pub async fn generate_embeddings_gcp(index_dir: String, max_files: Option<usize>
         .ok_or_else(|| "GCP token missing access token".to_string())?
         .to_string();
 
-    let client = reqwest::Client::new();
-    let url = config.endpoint.unwrap_or_else(|| format!(
+    let url = config.endpoint.clone().unwrap_or_else(|| format!(
         "https://{}-aiplatform.googleapis.com/v1/projects/{}/locations/{}/publishers/google/models/{}:predict",
         config.location, config.project_id, config.location, config.model_id
     ));
+    Ok(GcpVertexProvider {
+        client: reqwest::Client::new(),
+        url,
+        bearer,
+        model_id: config.model_id.clone(),
+    })
+}
 
-    let mut embeddings: Vec<FileEmbedding> = Vec::new();
-    let mut generated_count = 0;
-
-    for file in files_to_process {
-        let content = match fs::read_to_string(&file.path) {
-            Ok(c) => c,
-            Err(_) => continue,
-        };
-        if content.trim().is_empty() {
-            continue;
-        }
-
-        let content_hash = format!("{:x}", md5_hash(&content));
-
-        let response = client
-            .post(&url)
-            .bearer_auth(&bearer)
-            .json(&serde_json::json!({
-                "instances": [{ "content": content }]
-            }))
-            .send()
-            .await
-            .map_err(|e| format!("GCP request failed: {}", e))?;
-
-        if response.status().is_success() {
-            let json: serde_json::Value = response.json().await.map_err(|e| format!("Failed to parse GCP response: {}", e))?;
-            if let Some(embedding_values) = json["predictions"][0]["embedding"]["values"].as_array() {
-                let emb_vec: Vec<f32> = embedding_values.iter().filter_map(|v| v.as_f64().map(|f| f as f32)).collect();
-                embeddings.push(FileEmbedding {
-                    path: file.path.clone(),
-                    embedding: emb_vec,
-                    content_hash,
-                });
-                generated_count += 1;
-            }
-        } else {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            log_error(index_path, "gcp_api_error", Some(&file.path), &text, Some(&status.to_string()));
-        }
+/// Generate embeddings using a local Ollama server via the shared
+/// `run_provider_embedding_job` driver (atomic saves, persistent
+/// content-hash cache, real retry/backoff). This is what `ProviderKind::Local`
+/// dispatches to: an unreachable server is a hard error rather than a silent
+/// fallback, so a broken Ollama setup surfaces instead of quietly indexing
+/// deterministic noise.
+pub async fn generate_embeddings_ollama(
+    index_dir: String,
+    max_files: Option<usize>,
+    batch_size: Option<usize>,
+    model_name: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let model = model_name.unwrap_or_else(default_local_model_name);
+    let probe_client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    if !ollama_reachable(&probe_client).await {
+        return Err("Ollama server not reachable at http://localhost:11434".to_string());
     }
 
-    let final_data = EmbeddingsData {
-        embeddings: embeddings.clone(),
-        model: config.model_id.clone(),
-        created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-    };
-
-    let json = serde_json::to_string_pretty(&final_data).map_err(|e| format!("Failed to serialize embeddings: {}", e))?;
-    fs::write(&embeddings_file, json).map_err(|e| format!("Failed to write embeddings file: {}", e))?;
-
-    Ok(serde_json::json!({
-        "embeddings_generated": generated_count,
-        "cached_count": 0,
-        "message": format!("Generated {} embeddings using GCP.", generated_count)
-    }))
+    let provider = OllamaProvider::new(model)?;
+    run_provider_embedding_job(&index_dir, &provider, max_files, batch_size).await
 }
 
 /// Get embedding progress
@@ -964,91 +1653,145 @@ pub async fn clear_error_log(index_dir: String) -> Result<serde_json::Value, Str
 
 /// Create clusters using k-means algorithm
 #[tauri::command]
-pub async fn create_clusters(index_dir: String, num_clusters: Option<usize>) -> Result<serde_json::Value, String> {
+pub async fn create_clusters(index_dir: String, num_clusters: Option<usize>) -> Result<serde_json::Value, ResponseError> {
     println!("[RUST] create_clusters called for: {}", index_dir);
-    
+
     let index_path = Path::new(&index_dir);
     let embeddings_file = index_path.join("embeddings.json");
     let clusters_file = index_path.join("clusters.json");
-    
+
     // Load embeddings
     if !embeddings_file.exists() {
-        return Err("Embeddings not found. Please generate embeddings first.".to_string());
+        return Err(ResponseError::new("path_not_found", "Embeddings not found. Please generate embeddings first."));
     }
-    
+
     let content = fs::read_to_string(&embeddings_file)
-        .map_err(|e| format!("Failed to read embeddings: {}", e))?;
+        .map_err(|e| ResponseError::new("internal_error", format!("Failed to read embeddings: {}", e)))?;
     let embeddings_data: EmbeddingsData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse embeddings: {}", e))?;
-    
+        .map_err(|e| ResponseError::new("config_parse_error", format!("Failed to parse embeddings: {}", e)))?;
+
     if embeddings_data.embeddings.is_empty() {
-        return Err("No embeddings found. Please generate embeddings first.".to_string());
+        return Err(ResponseError::new("path_not_found", "No embeddings found. Please generate embeddings first."));
     }
     
-    // Determine number of clusters (default: sqrt of file count, min 2, max 20)
-    let k = num_clusters.unwrap_or_else(|| {
-        let sqrt = (embeddings_data.embeddings.len() as f64).sqrt() as usize;
-        sqrt.max(2).min(20)
-    });
-    
-    println!("[RUST] Clustering {} files into {} clusters", embeddings_data.embeddings.len(), k);
-    
-    // Run k-means clustering
-    let clusters = kmeans_cluster(&embeddings_data.embeddings, k);
-    
+    let total = embeddings_data.embeddings.len();
+    let (clusters, chosen_k, silhouette) = match num_clusters {
+        Some(k) => {
+            println!("[RUST] Clustering {} files into {} clusters", total, k);
+            let (assignments, centroids) = kmeans_cluster(&embeddings_data.embeddings, k);
+            let silhouette = mean_silhouette(&embeddings_data.embeddings, &assignments, centroids.len());
+            (build_clusters(&embeddings_data.embeddings, &assignments, &centroids), k, silhouette)
+        }
+        None => {
+            // Auto-k: try a small range of k and keep whichever clustering
+            // maximizes the mean silhouette coefficient.
+            let max_k = 20.min(total.saturating_sub(1)).max(2);
+            println!("[RUST] Auto-selecting k in 2..={} by silhouette score for {} files", max_k, total);
+            let mut best: Option<(usize, f32, Vec<usize>, Vec<Vec<f32>>)> = None;
+            for k in 2..=max_k {
+                let (assignments, centroids) = kmeans_cluster(&embeddings_data.embeddings, k);
+                let score = mean_silhouette(&embeddings_data.embeddings, &assignments, centroids.len());
+                println!("[RUST]   k={} silhouette={:.4}", k, score);
+                if best.as_ref().map(|(_, best_score, _, _)| score > *best_score).unwrap_or(true) {
+                    best = Some((k, score, assignments, centroids));
+                }
+            }
+            let (k, score, assignments, centroids) = best.ok_or_else(|| ResponseError::new("config_incomplete", "Not enough embeddings to cluster"))?;
+            (build_clusters(&embeddings_data.embeddings, &assignments, &centroids), k, score)
+        }
+    };
+
     // Save clusters
     let clusters_data = ClustersData {
         clusters: clusters.clone(),
         created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
     };
-    
+
     let json = serde_json::to_string_pretty(&clusters_data)
-        .map_err(|e| format!("Failed to serialize clusters: {}", e))?;
-    
+        .map_err(|e| ResponseError::new("internal_error", format!("Failed to serialize clusters: {}", e)))?;
+
     fs::write(&clusters_file, json)
-        .map_err(|e| format!("Failed to write clusters file: {}", e))?;
-    
-    println!("[RUST] Clustering complete: {} clusters created", clusters.len());
-    
+        .map_err(|e| ResponseError::new("internal_error", format!("Failed to write clusters file: {}", e)))?;
+
+    println!("[RUST] Clustering complete: {} clusters created (k={}, silhouette={:.4})", clusters.len(), chosen_k, silhouette);
+
     Ok(serde_json::json!({
         "clusters_created": clusters.len(),
-        "total_files": embeddings_data.embeddings.len(),
-        "message": format!("Created {} clusters from {} files", clusters.len(), embeddings_data.embeddings.len())
+        "total_files": total,
+        "chosen_k": chosen_k,
+        "silhouette_score": silhouette,
+        "message": format!("Created {} clusters from {} files (k={}, silhouette={:.4})", clusters.len(), total, chosen_k, silhouette)
     }))
 }
 
-/// K-means clustering implementation
-fn kmeans_cluster(embeddings: &[FileEmbedding], k: usize) -> Vec<Cluster> {
+/// Seed k centroids via k-means++: the first is uniformly random, then each
+/// subsequent centroid is sampled with probability proportional to its
+/// squared cosine distance to the nearest already-chosen centroid (D²
+/// weighting), which spreads seeds apart and speeds up convergence versus
+/// pure uniform-random seeding.
+fn kmeans_plus_plus_init(embeddings: &[FileEmbedding], k: usize, rng: &mut impl Rng) -> Vec<Vec<f32>> {
+    let mut centroids: Vec<Vec<f32>> = Vec::with_capacity(k);
+    let first = rng.gen_range(0..embeddings.len());
+    centroids.push(embeddings[first].embedding.clone());
+
+    while centroids.len() < k {
+        let weights: Vec<f32> = embeddings
+            .iter()
+            .map(|emb| {
+                centroids
+                    .iter()
+                    .map(|c| {
+                        let d = cosine_distance(&emb.embedding, c);
+                        d * d
+                    })
+                    .fold(f32::MAX, f32::min)
+            })
+            .collect();
+        let total_weight: f32 = weights.iter().sum();
+        if total_weight <= 1e-10 {
+            // All remaining points coincide with a chosen centroid; fall back
+            // to uniform sampling rather than dividing by zero.
+            let idx = rng.gen_range(0..embeddings.len());
+            centroids.push(embeddings[idx].embedding.clone());
+            continue;
+        }
+        let mut target = rng.gen_range(0.0..total_weight);
+        let mut chosen = embeddings.len() - 1;
+        for (i, w) in weights.iter().enumerate() {
+            if target < *w {
+                chosen = i;
+                break;
+            }
+            target -= w;
+        }
+        centroids.push(embeddings[chosen].embedding.clone());
+    }
+
+    centroids
+}
+
+/// K-means clustering over cosine distance, k-means++ seeded. Returns the
+/// final per-point cluster assignments and centroids; callers turn that into
+/// `Cluster`s via `build_clusters` once they've picked (or swept) k.
+fn kmeans_cluster(embeddings: &[FileEmbedding], k: usize) -> (Vec<usize>, Vec<Vec<f32>>) {
     if embeddings.is_empty() || k == 0 {
-        return Vec::new();
+        return (Vec::new(), Vec::new());
     }
-    
+
     let dim = embeddings[0].embedding.len();
     let mut rng = rand::thread_rng();
-    
-    // Initialize centroids randomly from the embeddings
-    let mut centroids: Vec<Vec<f32>> = Vec::with_capacity(k);
-    let mut used_indices: Vec<usize> = Vec::new();
-    
-    for _ in 0..k.min(embeddings.len()) {
-        let mut idx = rng.gen_range(0..embeddings.len());
-        while used_indices.contains(&idx) {
-            idx = rng.gen_range(0..embeddings.len());
-        }
-        used_indices.push(idx);
-        centroids.push(embeddings[idx].embedding.clone());
-    }
-    
-    // Run k-means for 50 iterations
+    let k = k.min(embeddings.len());
+    let mut centroids = kmeans_plus_plus_init(embeddings, k, &mut rng);
+
     let mut assignments: Vec<usize> = vec![0; embeddings.len()];
-    
+
     for iteration in 0..50 {
         // Assign each embedding to nearest centroid
         let mut changed = false;
         for (i, emb) in embeddings.iter().enumerate() {
             let mut min_dist = f32::MAX;
             let mut min_idx = 0;
-            
+
             for (j, centroid) in centroids.iter().enumerate() {
                 let dist = cosine_distance(&emb.embedding, centroid);
                 if dist < min_dist {
@@ -1056,23 +1799,23 @@ fn kmeans_cluster(embeddings: &[FileEmbedding], k: usize) -> Vec<Cluster> {
                     min_idx = j;
                 }
             }
-            
+
             if assignments[i] != min_idx {
                 assignments[i] = min_idx;
                 changed = true;
             }
         }
-        
+
         if !changed {
             println!("[RUST] K-means converged at iteration {}", iteration);
             break;
         }
-        
+
         // Update centroids
         for j in 0..centroids.len() {
             let mut new_centroid = vec![0.0f32; dim];
             let mut count = 0;
-            
+
             for (i, emb) in embeddings.iter().enumerate() {
                 if assignments[i] == j {
                     for (d, val) in emb.embedding.iter().enumerate() {
@@ -1081,7 +1824,7 @@ fn kmeans_cluster(embeddings: &[FileEmbedding], k: usize) -> Vec<Cluster> {
                     count += 1;
                 }
             }
-            
+
             if count > 0 {
                 for val in new_centroid.iter_mut() {
                     *val /= count as f32;
@@ -1090,10 +1833,15 @@ fn kmeans_cluster(embeddings: &[FileEmbedding], k: usize) -> Vec<Cluster> {
             }
         }
     }
-    
-    // Build cluster results
-    let mut clusters: Vec<Cluster> = Vec::with_capacity(k);
-    
+
+    (assignments, centroids)
+}
+
+/// Turn k-means assignments/centroids into labeled `Cluster`s, dropping any
+/// centroid left with no assigned points.
+fn build_clusters(embeddings: &[FileEmbedding], assignments: &[usize], centroids: &[Vec<f32>]) -> Vec<Cluster> {
+    let mut clusters: Vec<Cluster> = Vec::with_capacity(centroids.len());
+
     for j in 0..centroids.len() {
         let file_paths: Vec<String> = embeddings
             .iter()
@@ -1101,7 +1849,7 @@ fn kmeans_cluster(embeddings: &[FileEmbedding], k: usize) -> Vec<Cluster> {
             .filter(|(i, _)| assignments[*i] == j)
             .map(|(_, emb)| emb.path.clone())
             .collect();
-        
+
         if !file_paths.is_empty() {
             let label = generate_cluster_label(&file_paths);
             clusters.push(Cluster {
@@ -1112,10 +1860,61 @@ fn kmeans_cluster(embeddings: &[FileEmbedding], k: usize) -> Vec<Cluster> {
             });
         }
     }
-    
+
     clusters
 }
 
+/// Mean silhouette coefficient over all points: for each point, `s = (b - a)
+/// / max(a, b)` where `a` is its mean cosine distance to points in its own
+/// cluster and `b` is the mean distance to points in the nearest other
+/// cluster. Used to pick k automatically when the caller doesn't specify one.
+/// Singleton clusters (`a` undefined) score 0 for that point, matching the
+/// usual convention.
+fn mean_silhouette(embeddings: &[FileEmbedding], assignments: &[usize], k: usize) -> f32 {
+    if embeddings.len() < 2 || k < 2 {
+        return 0.0;
+    }
+
+    let mut total = 0.0f32;
+    for (i, emb) in embeddings.iter().enumerate() {
+        let own_cluster = assignments[i];
+        let mut own_sum = 0.0f32;
+        let mut own_count = 0usize;
+        let mut other_sums: HashMap<usize, (f32, usize)> = HashMap::new();
+
+        for (j, other) in embeddings.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            let dist = cosine_distance(&emb.embedding, &other.embedding);
+            if assignments[j] == own_cluster {
+                own_sum += dist;
+                own_count += 1;
+            } else {
+                let entry = other_sums.entry(assignments[j]).or_insert((0.0, 0));
+                entry.0 += dist;
+                entry.1 += 1;
+            }
+        }
+
+        let a = if own_count > 0 { own_sum / own_count as f32 } else { 0.0 };
+        let b = other_sums
+            .values()
+            .filter(|(_, count)| *count > 0)
+            .map(|(sum, count)| sum / *count as f32)
+            .fold(f32::MAX, f32::min);
+
+        let s = if own_count == 0 || b == f32::MAX {
+            0.0
+        } else {
+            (b - a) / a.max(b)
+        };
+        total += s;
+    }
+
+    total / embeddings.len() as f32
+}
+
 /// Generate a descriptive label for a cluster based on its files
 fn generate_cluster_label(file_paths: &[String]) -> String {
     use std::collections::HashMap;
@@ -1248,80 +2047,805 @@ fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
     1.0 - similarity
 }
 
-/// Search indexed files by query string
-#[tauri::command]
-pub async fn search(
-    query: String,
-    index_dir: String,
-    top_k: usize,
-    _semantic_weight: f32,
-) -> Result<serde_json::Value, String> {
-    let index_path = Path::new(&index_dir);
-    let index_file = index_path.join("index.json");
-    
-    if !index_file.exists() {
-        return Err("Index not found. Please scan a directory first.".to_string());
-    }
+const HNSW_M: usize = 16;
+const HNSW_EF_CONSTRUCTION: usize = 100;
 
-    let content = fs::read_to_string(&index_file)
-        .map_err(|e| format!("Failed to read index: {}", e))?;
-    
-    let index_data: IndexData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse index: {}", e))?;
+/// A single node in the HNSW graph: the top layer it was assigned, and its
+/// greedily-pruned neighbor ids at each layer `0..=layer`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct HnswNode {
+    layer: usize,
+    neighbors: Vec<Vec<usize>>,
+}
 
-    let query_lower = query.to_lowercase();
-    let mut results: Vec<SearchResult> = Vec::new();
+/// Persisted Hierarchical Navigable Small World graph over `FileEmbedding`
+/// vectors, built by `build_ann_index` and queried by `ann_search` so
+/// similarity lookups stay sub-linear as the indexed corpus grows, instead
+/// of the brute-force scan every other retrieval path here uses.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AnnIndex {
+    paths: Vec<String>,
+    start_bytes: Vec<usize>,
+    end_bytes: Vec<usize>,
+    vectors: Vec<Vec<f32>>,
+    nodes: Vec<HnswNode>,
+    entry_point: usize,
+    m: usize,
+    ef_construction: usize,
+    created_at: String,
+}
 
-    for file in &index_data.files {
-        let name_lower = file.name.to_lowercase();
-        let path_lower = file.path.to_lowercase();
-        
-        // Simple text matching score
-        let mut score: f32 = 0.0;
-        
-        if name_lower.contains(&query_lower) {
-            score += 1.0;
-        }
-        if path_lower.contains(&query_lower) {
-            score += 0.5;
-        }
-
-        // Try to search within file content
-        if let Ok(content) = fs::read_to_string(&file.path) {
-            if content.to_lowercase().contains(&query_lower) {
-                score += 0.8;
-                
-                // Get a preview snippet
-                let content_lower = content.to_lowercase();
-                if let Some(pos) = content_lower.find(&query_lower) {
-                    let start = pos.saturating_sub(50);
-                    let end = (pos + query.len() + 50).min(content.len());
-                    let preview = &content[start..end];
-                    
-                    if score > 0.0 {
-                        results.push(SearchResult {
-                            path: file.path.clone(),
-                            name: file.name.clone(),
-                            score,
-                            preview: Some(preview.trim().to_string()),
-                        });
+/// Sample a node's top layer with exponentially decaying probability
+/// (`level = floor(-ln(U) * mL)`, `mL = 1/ln(m)`), the standard HNSW level
+/// assignment that keeps higher layers sparse.
+fn hnsw_random_layer(m: usize, rng: &mut impl Rng) -> usize {
+    let ml = 1.0 / (m as f64).ln();
+    let r: f64 = rng.gen_range(1e-9..1.0);
+    (-r.ln() * ml).floor() as usize
+}
+
+/// Greedy best-first search within a single HNSW layer: starting from
+/// `entry_points`, repeatedly expand the closest unvisited candidate's
+/// neighbors until no unvisited neighbor improves on the worst of the best
+/// `ef` candidates found so far. Returns up to `ef` `(id, distance)` pairs
+/// sorted by ascending cosine distance.
+fn hnsw_search_layer(
+    vectors: &[Vec<f32>],
+    nodes: &[HnswNode],
+    query: &[f32],
+    entry_points: &[usize],
+    layer: usize,
+    ef: usize,
+) -> Vec<(usize, f32)> {
+    let mut visited: std::collections::HashSet<usize> = entry_points.iter().cloned().collect();
+    let mut result: Vec<(usize, f32)> = entry_points
+        .iter()
+        .map(|&id| (id, cosine_distance(query, &vectors[id])))
+        .collect();
+    result.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    let mut frontier = result.clone();
+
+    while !frontier.is_empty() {
+        let (current_id, current_dist) = frontier.remove(0);
+        let worst = result.last().map(|(_, d)| *d).unwrap_or(f32::MAX);
+        if result.len() >= ef && current_dist > worst {
+            break;
+        }
+        if let Some(neighbor_ids) = nodes[current_id].neighbors.get(layer) {
+            for &neighbor_id in neighbor_ids {
+                if visited.insert(neighbor_id) {
+                    let dist = cosine_distance(query, &vectors[neighbor_id]);
+                    frontier.push((neighbor_id, dist));
+                    frontier.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+                    result.push((neighbor_id, dist));
+                    result.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+                    result.truncate(ef.max(1));
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// Build an HNSW graph over every `FileEmbedding` and persist it as
+/// `ann_index.json` beside `embeddings.json`. Nodes are inserted one at a
+/// time: each descends greedily from the current top layer down to its own
+/// assigned layer, then at each layer from its own down to 0 it gathers
+/// `ef_construction` candidates and connects bidirectionally to the closest
+/// `M`, pruning any neighbor list that grows past `M` back down by cosine
+/// distance.
+pub async fn build_ann_index(index_dir: String) -> Result<serde_json::Value, String> {
+    let index_path = Path::new(&index_dir);
+    let embeddings_file = index_path.join("embeddings.json");
+    let ann_file = index_path.join("ann_index.json");
+
+    if !embeddings_file.exists() {
+        return Err("Embeddings not found. Please generate embeddings first.".to_string());
+    }
+    let content = fs::read_to_string(&embeddings_file).map_err(|e| format!("Failed to read embeddings: {}", e))?;
+    let embeddings_data: EmbeddingsData = serde_json::from_str(&content).map_err(|e| format!("Failed to parse embeddings: {}", e))?;
+    if embeddings_data.embeddings.is_empty() {
+        return Err("No embeddings found. Please generate embeddings first.".to_string());
+    }
+
+    let m = HNSW_M;
+    let ef_construction = HNSW_EF_CONSTRUCTION;
+    let vectors: Vec<Vec<f32>> = embeddings_data.embeddings.iter().map(|e| e.embedding.clone()).collect();
+    let paths: Vec<String> = embeddings_data.embeddings.iter().map(|e| e.path.clone()).collect();
+    let start_bytes: Vec<usize> = embeddings_data.embeddings.iter().map(|e| e.start_byte).collect();
+    let end_bytes: Vec<usize> = embeddings_data.embeddings.iter().map(|e| e.end_byte).collect();
+
+    let mut rng = rand::thread_rng();
+    let mut nodes: Vec<HnswNode> = Vec::with_capacity(vectors.len());
+    let mut entry_point = 0usize;
+    let mut max_layer = 0usize;
+
+    for (id, vector) in vectors.iter().enumerate() {
+        let layer = hnsw_random_layer(m, &mut rng);
+
+        if id == 0 {
+            nodes.push(HnswNode { layer, neighbors: vec![Vec::new(); layer + 1] });
+            entry_point = 0;
+            max_layer = layer;
+            continue;
+        }
+
+        let mut node = HnswNode { layer, neighbors: vec![Vec::new(); layer + 1] };
+
+        // Descend greedily from the top layer down to `layer + 1`, keeping
+        // only the single nearest point found at each level as the entry
+        // point for the level below.
+        let mut current = vec![entry_point];
+        for l in (layer + 1..=max_layer).rev() {
+            current = hnsw_search_layer(&vectors, &nodes, vector, &current, l, 1)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+        }
+
+        // From `min(layer, max_layer)` down to 0, gather ef_construction
+        // candidates and connect bidirectionally to the M closest.
+        for l in (0..=layer.min(max_layer)).rev() {
+            let candidates = hnsw_search_layer(&vectors, &nodes, vector, &current, l, ef_construction);
+            let chosen: Vec<usize> = candidates.iter().take(m).map(|(cid, _)| *cid).collect();
+            node.neighbors[l] = chosen.clone();
+            for &neighbor_id in &chosen {
+                let neighbor_neighbors = &mut nodes[neighbor_id].neighbors;
+                if l < neighbor_neighbors.len() {
+                    neighbor_neighbors[l].push(id);
+                    if neighbor_neighbors[l].len() > m {
+                        let mut scored: Vec<(usize, f32)> = neighbor_neighbors[l]
+                            .iter()
+                            .map(|&nid| (nid, cosine_distance(&vectors[neighbor_id], &vectors[nid])))
+                            .collect();
+                        scored.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+                        scored.truncate(m);
+                        neighbor_neighbors[l] = scored.into_iter().map(|(nid, _)| nid).collect();
                     }
-                    continue;
                 }
             }
+            current = candidates.into_iter().map(|(cid, _)| cid).collect();
         }
 
-        if score > 0.0 {
-            results.push(SearchResult {
-                path: file.path.clone(),
-                name: file.name.clone(),
+        nodes.push(node);
+        if layer > max_layer {
+            max_layer = layer;
+            entry_point = id;
+        }
+    }
+
+    let node_count = nodes.len();
+    let ann_index = AnnIndex {
+        paths,
+        start_bytes,
+        end_bytes,
+        vectors,
+        nodes,
+        entry_point,
+        m,
+        ef_construction,
+        created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+
+    let json = serde_json::to_string_pretty(&ann_index).map_err(|e| format!("Failed to serialize ANN index: {}", e))?;
+    fs::write(&ann_file, json).map_err(|e| format!("Failed to write ANN index: {}", e))?;
+
+    println!("[RUST] Built HNSW index over {} vectors (max_layer={})", node_count, max_layer);
+    Ok(serde_json::json!({
+        "nodes_indexed": node_count,
+        "max_layer": max_layer,
+        "message": format!("Built HNSW index over {} vectors", node_count)
+    }))
+}
+
+/// Load a previously built `ann_index.json` beside `embeddings.json`, if any.
+fn load_ann_index(index_path: &Path) -> Option<AnnIndex> {
+    let ann_file = index_path.join("ann_index.json");
+    if !ann_file.exists() {
+        return None;
+    }
+    let content = fs::read_to_string(&ann_file).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Approximate top-k nearest-neighbor search over `ann_index`: descend
+/// greedily from the top layer down to layer 1 (keeping only the single
+/// best entry point at each level), then widen to a candidate list of size
+/// `ef` at layer 0 and return the top `k` `(id, distance)` pairs.
+fn ann_index_search(ann_index: &AnnIndex, query_embedding: &[f32], k: usize, ef: Option<usize>) -> Vec<(usize, f32)> {
+    if ann_index.nodes.is_empty() {
+        return Vec::new();
+    }
+    let ef = ef.unwrap_or_else(|| k.max(ann_index.ef_construction).min(200));
+    let max_layer = ann_index.nodes[ann_index.entry_point].layer;
+
+    let mut current = vec![ann_index.entry_point];
+    for l in (1..=max_layer).rev() {
+        current = hnsw_search_layer(&ann_index.vectors, &ann_index.nodes, query_embedding, &current, l, 1)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+    }
+    hnsw_search_layer(&ann_index.vectors, &ann_index.nodes, query_embedding, &current, 0, ef.max(k))
+        .into_iter()
+        .take(k)
+        .collect()
+}
+
+/// Approximate top-k nearest-neighbor search over a previously built
+/// `ann_index.json`: descend greedily from the top layer down to layer 1
+/// (keeping only the single best entry point at each level), then widen to
+/// a candidate list of size `ef` at layer 0 and return the top `k`.
+pub async fn ann_search(index_dir: String, query_embedding: Vec<f32>, k: usize, ef: Option<usize>) -> Result<serde_json::Value, String> {
+    let index_path = Path::new(&index_dir);
+    let Some(ann_index) = load_ann_index(index_path) else {
+        return Err("ANN index not found. Please build it first with build_ann_index.".to_string());
+    };
+    if ann_index.nodes.is_empty() {
+        return Ok(serde_json::json!({ "results": [] }));
+    }
+
+    let candidates = ann_index_search(&ann_index, &query_embedding, k, ef);
+
+    let results: Vec<serde_json::Value> = candidates
+        .into_iter()
+        .map(|(id, dist)| {
+            serde_json::json!({
+                "path": ann_index.paths[id],
+                "start_byte": ann_index.start_bytes[id],
+                "end_byte": ann_index.end_bytes[id],
+                "score": 1.0 - dist,
+            })
+        })
+        .collect();
+
+    Ok(serde_json::json!({ "results": results }))
+}
+
+/// Search indexed files by query string, fusing four independent ranked
+/// signals - filename overlap, path overlap, content BM25, and semantic
+/// (cosine similarity) - via Reciprocal Rank Fusion: `score(d) = Σ_r weight_r
+/// / (k + rank_r(d))` over whichever lists ranked `d` at all, `k=60`. RRF
+/// is scale-free, so it combines these without the fragile score
+/// normalization tuning an additive blend would need, and degrades
+/// gracefully when a signal (e.g. embeddings) is missing entirely.
+/// `semantic_ratio` (0.0-1.0) weights the semantic list's contribution; the
+/// remaining `1.0 - semantic_ratio` is split evenly across the three
+/// lexical lists. The HTTP `/search` route defaults it to 0.5, a balanced
+/// keyword/semantic blend.
+#[tauri::command]
+pub async fn search(
+    query: String,
+    index_dir: String,
+    top_k: usize,
+    semantic_ratio: f32,
+    max_typos: Option<usize>,
+    filter: Option<SearchFilter>,
+) -> Result<serde_json::Value, String> {
+    const RRF_K: f32 = 60.0;
+    let semantic_ratio = semantic_ratio.clamp(0.0, 1.0);
+
+    let index_path = Path::new(&index_dir);
+    let mut index_data = load_index_data(index_path)?;
+    if let Some(filter) = &filter {
+        index_data.files.retain(|f| file_matches_filter(f, filter));
+    }
+    let candidates = dual_retrieve(index_path, &index_data, &query, max_typos).await?;
+
+    // Four independent signals, each ranked on its own terms rather than
+    // summed into one ad-hoc score: filename overlap, path overlap, content
+    // BM25, and semantic similarity. `rrf_rank` turns a "higher is better"
+    // score column into a 1-based rank map (absent entries didn't match at
+    // all and are simply left out of that list's contribution).
+    fn rrf_rank(candidates: &[RetrievalCandidate], score_of: impl Fn(&RetrievalCandidate) -> f32) -> HashMap<usize, usize> {
+        let mut order: Vec<usize> = (0..candidates.len()).filter(|&i| score_of(&candidates[i]) > 0.0).collect();
+        order.sort_by(|&a, &b| score_of(&candidates[b]).partial_cmp(&score_of(&candidates[a])).unwrap_or(std::cmp::Ordering::Equal));
+        order.into_iter().enumerate().map(|(rank, idx)| (idx, rank + 1)).collect()
+    }
+
+    let name_rank = rrf_rank(&candidates, |c| c.name_score);
+    let path_rank = rrf_rank(&candidates, |c| c.path_score);
+    let content_rank = rrf_rank(&candidates, |c| c.bm25);
+    let semantic_rank = rrf_rank(&candidates, |c| c.vector);
+
+    // `semantic_ratio` keeps its existing meaning (semantic share vs.
+    // keyword share of the total); the keyword share is now split evenly
+    // across the three lexical lists instead of one merged lexical list.
+    let lexical_weight = (1.0 - semantic_ratio) / 3.0;
+
+    let mut results: Vec<SearchResult> = candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, candidate)| {
+            let mut score = 0.0f32;
+            if let Some(&rank) = name_rank.get(&idx) {
+                score += lexical_weight / (RRF_K + rank as f32);
+            }
+            if let Some(&rank) = path_rank.get(&idx) {
+                score += lexical_weight / (RRF_K + rank as f32);
+            }
+            if let Some(&rank) = content_rank.get(&idx) {
+                score += lexical_weight / (RRF_K + rank as f32);
+            }
+            if let Some(&rank) = semantic_rank.get(&idx) {
+                score += semantic_ratio / (RRF_K + rank as f32);
+            }
+            if score <= 0.0 {
+                return None;
+            }
+            Some(SearchResult {
+                path: candidate.file.path.clone(),
+                name: candidate.file.name.clone(),
                 score,
-                preview: None,
-            });
+                preview: candidate.preview.clone(),
+            })
+        })
+        .collect();
+
+    // Facet counts cover every matching file (before the top_k page cut), so
+    // a UI can show accurate drill-down totals ("142 in .rs") rather than
+    // counts scoped to just the returned page.
+    let files_by_path: HashMap<&str, &FileEntry> =
+        index_data.files.iter().map(|f| (f.path.as_str(), f)).collect();
+    let mut extension_facets: HashMap<String, usize> = HashMap::new();
+    let mut day_facets: HashMap<String, usize> = HashMap::new();
+    for result in &results {
+        if let Some(file) = files_by_path.get(result.path.as_str()) {
+            *extension_facets.entry(file.extension.clone()).or_insert(0) += 1;
+            *day_facets.entry(modified_date_part(&file.modified).to_string()).or_insert(0) += 1;
+        }
+    }
+
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    results.truncate(top_k);
+
+    Ok(serde_json::json!({
+        "results": results,
+        "facets": {
+            "extensions": extension_facets,
+            "days": day_facets,
+        }
+    }))
+}
+
+fn load_index_data(index_path: &Path) -> Result<IndexData, String> {
+    let index_file = index_path.join("index.json");
+    if !index_file.exists() {
+        return Err("Index not found. Please scan a directory first.".to_string());
+    }
+    let content = fs::read_to_string(&index_file)
+        .map_err(|e| format!("Failed to read index: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse index: {}", e))
+}
+
+/// Tokenize every file's content once and build a term -> postings map, so
+/// repeated queries never need to re-read the indexed files themselves (only
+/// the much smaller `inverted.json`). Name and path are deliberately left
+/// out of this index: `search` ranks them as their own RRF signals via
+/// `token_overlap_score` instead of folding them into the content BM25 score.
+fn build_inverted_index(files: &[FileEntry]) -> InvertedIndex {
+    let mut postings: HashMap<String, Vec<PostingEntry>> = HashMap::new();
+    let mut doc_paths = Vec::with_capacity(files.len());
+    let mut doc_lengths = Vec::with_capacity(files.len());
+
+    for (doc_id, file) in files.iter().enumerate() {
+        let content = read_indexed_content(&file.path).unwrap_or_default();
+        let tokens = tokenize(&content);
+
+        let mut tf: HashMap<String, usize> = HashMap::new();
+        for t in &tokens {
+            *tf.entry(t.clone()).or_insert(0) += 1;
+        }
+        for (term, term_frequency) in tf {
+            postings.entry(term).or_default().push(PostingEntry { doc_id, term_frequency });
+        }
+
+        doc_paths.push(file.path.clone());
+        doc_lengths.push(tokens.len());
+    }
+
+    let avg_doc_length = if doc_lengths.is_empty() {
+        1.0
+    } else {
+        doc_lengths.iter().sum::<usize>() as f32 / doc_lengths.len() as f32
+    };
+
+    InvertedIndex {
+        postings,
+        doc_paths,
+        doc_lengths,
+        avg_doc_length,
+        created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    }
+}
+
+/// Build and persist `inverted.json` for the current file list. Errors here
+/// are non-fatal to the caller (scan/watcher) since `dual_retrieve` falls
+/// back to a live per-query scan when this file is missing.
+fn write_inverted_index(index_path: &Path, files: &[FileEntry]) -> Result<(), String> {
+    let inverted = build_inverted_index(files);
+    let json = serde_json::to_string_pretty(&inverted)
+        .map_err(|e| format!("Failed to serialize inverted index: {}", e))?;
+    fs::write(index_path.join("inverted.json"), json)
+        .map_err(|e| format!("Failed to write inverted index: {}", e))
+}
+
+/// Load the persisted BM25 inverted index, if present. Indexes scanned
+/// before this existed (or a corrupt file) simply yield `None`, which sends
+/// `dual_retrieve` down its live-scan fallback path.
+fn load_inverted_index(index_path: &Path) -> Option<InvertedIndex> {
+    let inverted_file = index_path.join("inverted.json");
+    if !inverted_file.exists() {
+        return None;
+    }
+    let content = fs::read_to_string(&inverted_file).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Edit-distance budget for typo-tolerant matching, scaling with token
+/// length so a single-character difference in a short token (where it likely
+/// changes the meaning) is never treated as a typo.
+fn typo_budget(len: usize) -> usize {
+    if len <= 4 {
+        0
+    } else if len <= 8 {
+        1
+    } else {
+        2
+    }
+}
+
+/// Bounded Levenshtein edit distance between `a` and `b`. Returns `None` as
+/// soon as the distance is certain to exceed `max` (a length gap bigger than
+/// `max`, or every cell in the current DP row already over budget), so
+/// scanning a large vocabulary for fuzzy candidates stays cheap.
+fn bounded_levenshtein(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut curr = vec![0usize; b.len() + 1];
+        curr[0] = i;
+        let mut row_min = curr[0];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+            row_min = row_min.min(curr[j]);
+        }
+        if row_min > max {
+            return None;
+        }
+        prev = curr;
+    }
+
+    let dist = prev[b.len()];
+    if dist <= max {
+        Some(dist)
+    } else {
+        None
+    }
+}
+
+/// Add one term's BM25 contribution (Okapi, `k1=1.2`, `b=0.75`) into
+/// `scores_by_doc`, scaled by `penalty` (1.0 for an exact match; <1.0 for a
+/// typo-tolerant fuzzy match, so exact matches still outrank corrected ones).
+fn apply_term_score(inv: &InvertedIndex, n: f32, avgdl: f32, term: &str, penalty: f32, scores_by_doc: &mut HashMap<usize, f32>) {
+    const K1: f32 = 1.2;
+    const B: f32 = 0.75;
+    let Some(entries) = inv.postings.get(term) else { return };
+    let df = entries.len() as f32;
+    let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+    for entry in entries {
+        let dl = *inv.doc_lengths.get(entry.doc_id).unwrap_or(&0) as f32;
+        let tf = entry.term_frequency as f32;
+        let contribution = penalty * idf * (tf * (K1 + 1.0)) / (tf + K1 * (1.0 - B + B * dl / avgdl));
+        *scores_by_doc.entry(entry.doc_id).or_insert(0.0) += contribution;
+    }
+}
+
+/// Score every document that shares at least one query term (exactly, or
+/// within a length-scaled Levenshtein budget) using the persisted postings.
+/// Documents with no matching term are simply absent from the returned map
+/// (caller treats a missing entry as score 0). `max_typos` caps the
+/// per-length typo budget (`Some(0)` disables fuzzy matching entirely;
+/// `None` uses the full length-scaled budget).
+fn bm25_scores_from_inverted(inv: &InvertedIndex, query_terms: &[String], max_typos: Option<usize>) -> HashMap<String, f32> {
+    let n = inv.doc_paths.len() as f32;
+    let avgdl = inv.avg_doc_length.max(1.0);
+
+    // Candidate generation: bucket the vocabulary by token length once per
+    // query, so a fuzzy lookup only ever compares against lengths within its
+    // typo budget instead of walking the whole vocabulary per query term.
+    let mut by_length: HashMap<usize, Vec<&str>> = HashMap::new();
+    for term in inv.postings.keys() {
+        by_length.entry(term.chars().count()).or_default().push(term.as_str());
+    }
+
+    let mut scores_by_doc: HashMap<usize, f32> = HashMap::new();
+    for term in query_terms {
+        if inv.postings.contains_key(term.as_str()) {
+            apply_term_score(inv, n, avgdl, term, 1.0, &mut scores_by_doc);
+            continue;
+        }
+
+        let len = term.chars().count();
+        let budget = typo_budget(len).min(max_typos.unwrap_or(usize::MAX));
+        if budget == 0 {
+            continue;
+        }
+
+        for candidate_len in len.saturating_sub(budget)..=(len + budget) {
+            let Some(candidates) = by_length.get(&candidate_len) else { continue };
+            for &candidate in candidates {
+                let Some(dist) = bounded_levenshtein(term, candidate, budget) else { continue };
+                if dist == 0 {
+                    continue;
+                }
+                let penalty = 1.0 - (dist as f32 / (budget as f32 + 1.0));
+                apply_term_score(inv, n, avgdl, candidate, penalty, &mut scores_by_doc);
+            }
+        }
+    }
+
+    scores_by_doc
+        .into_iter()
+        .filter_map(|(doc_id, score)| inv.doc_paths.get(doc_id).map(|p| (p.clone(), score.max(0.0))))
+        .collect()
+}
+
+/// Same BM25 (+ typo-tolerant fuzzy) scoring as `bm25_scores_from_inverted`,
+/// but over a freshly built, throwaway inverted index. Used only as a
+/// fallback for indexes that predate `inverted.json` (or whose copy of it is
+/// missing/corrupt).
+fn compute_bm25_live(index_data: &IndexData, query_terms: &[String], max_typos: Option<usize>) -> HashMap<String, f32> {
+    let inv = build_inverted_index(&index_data.files);
+    bm25_scores_from_inverted(&inv, query_terms, max_typos)
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// Fraction of `query_terms` found in `tokens`, used as the filename-match
+/// and path-match RRF signals. Unlike BM25, name/path are short enough that
+/// IDF weighting isn't worth it - plain overlap ranks well and degrades to
+/// 0.0 (excluded from its list) when nothing matches.
+fn token_overlap_score(tokens: &[String], query_terms: &[String]) -> f32 {
+    if query_terms.is_empty() {
+        return 0.0;
+    }
+    let matches = query_terms.iter().filter(|t| tokens.contains(t)).count();
+    matches as f32 / query_terms.len() as f32
+}
+
+/// Min-max normalize a set of scores to [0, 1]. All-equal inputs (including
+/// the empty/all-zero case) map to 0.0 rather than dividing by zero.
+fn min_max_normalize(scores: &[f32]) -> Vec<f32> {
+    let min = scores.iter().cloned().fold(f32::INFINITY, f32::min);
+    let max = scores.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+    if range <= 1e-10 {
+        return vec![0.0; scores.len()];
+    }
+    scores.iter().map(|s| (s - min) / range).collect()
+}
+
+/// One file's standing against a query across every signal `search` fuses:
+/// filename/path token overlap, BM25 content score, and cosine-similarity
+/// semantic score. Each is 0.0 when that signal has nothing to say about
+/// this file (no overlap, no query-term match, or no embeddings yet).
+struct RetrievalCandidate {
+    file: FileEntry,
+    name_score: f32,
+    path_score: f32,
+    bm25: f32,
+    vector: f32,
+    preview: Option<String>,
+}
+
+/// Embed a single query string through whichever provider generated the
+/// index's embeddings (`provider_config.json`), so the query vector lands in
+/// the same space as `embeddings.json` instead of always using the local
+/// embedder regardless of how the index was built. Falls back to the local
+/// embedder (and its own deterministic fallback) on any cloud error, since a
+/// search should stay usable even if Azure/GCP credentials have expired.
+async fn embed_query_via_configured_provider(index_path: &Path, query: &str) -> Vec<f32> {
+    let provider_config = resolve_provider_config(index_path);
+    match provider_config.provider {
+        ProviderKind::Azure => match build_azure_provider(index_path) {
+            Ok(provider) => match provider.embed_batch(&[query.to_string()]).await {
+                Ok(mut vectors) if !vectors.is_empty() => return vectors.remove(0),
+                Ok(_) => {}
+                Err(e) => println!("[RUST] Azure query embedding failed, falling back to local: {}", e),
+            },
+            Err(e) => println!("[RUST] Azure query embedding failed, falling back to local: {}", e),
+        },
+        ProviderKind::Gcp => match build_gcp_provider(index_path).await {
+            Ok(provider) => match provider.embed_batch(&[query.to_string()]).await {
+                Ok(mut vectors) if !vectors.is_empty() => return vectors.remove(0),
+                Ok(_) => {}
+                Err(e) => println!("[RUST] GCP query embedding failed, falling back to local: {}", e),
+            },
+            Err(e) => println!("[RUST] GCP query embedding failed, falling back to local: {}", e),
+        },
+        ProviderKind::Local => {}
+    }
+
+    let model_name = provider_config.local_model.unwrap_or_else(default_local_model_name);
+    match reqwest::Client::builder().timeout(std::time::Duration::from_secs(30)).build() {
+        Ok(client) => embed_text_local(&client, &model_name, query, 512).await,
+        Err(_) => deterministic_embedding(query, 512),
+    }
+}
+
+/// Run the BM25 keyword retriever and the cosine-similarity semantic
+/// retriever over every indexed file, once, so `search` (RRF fusion) and
+/// `hybrid_search` (linear blend) can each combine the same two ranked
+/// lists their own way instead of re-tokenizing and re-embedding twice.
+async fn dual_retrieve(index_path: &Path, index_data: &IndexData, query: &str, max_typos: Option<usize>) -> Result<Vec<RetrievalCandidate>, String> {
+    let query_terms: Vec<String> = tokenize(query);
+    if query_terms.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // BM25: score from the persisted inverted index when available, so a
+    // query never has to re-read every indexed file's content from disk;
+    // only indexes scanned before `inverted.json` existed hit the live path.
+    let bm25_by_path: HashMap<String, f32> = match load_inverted_index(index_path) {
+        Some(inv) => bm25_scores_from_inverted(&inv, &query_terms, max_typos),
+        None => compute_bm25_live(index_data, &query_terms, max_typos),
+    };
+
+    // Vector side: embed the query once and compare against each file's
+    // best-matching chunk (or whole-file vector for pre-chunking entries).
+    let embeddings_file = index_path.join("embeddings.json");
+    let chunks_by_path: HashMap<String, Vec<FileEmbedding>> = if embeddings_file.exists() {
+        let raw = fs::read_to_string(&embeddings_file)
+            .map_err(|e| format!("Failed to read embeddings: {}", e))?;
+        let data: EmbeddingsData = serde_json::from_str(&raw)
+            .map_err(|e| format!("Failed to parse embeddings: {}", e))?;
+        let mut map: HashMap<String, Vec<FileEmbedding>> = HashMap::new();
+        for fe in data.embeddings {
+            map.entry(fe.path.clone()).or_default().push(fe);
+        }
+        map
+    } else {
+        HashMap::new()
+    };
+
+    let query_embedding = if chunks_by_path.is_empty() {
+        None
+    } else {
+        Some(embed_query_via_configured_provider(index_path, query).await)
+    };
+
+    // When an ANN index has been built, use it for the semantic signal so
+    // this stays sub-linear as the corpus grows; otherwise fall back to the
+    // exact brute-force max-over-chunks scan below.
+    let ann_index = load_ann_index(index_path);
+    let ann_vector_by_path: Option<HashMap<String, f32>> = match (&query_embedding, &ann_index) {
+        (Some(qe), Some(ann)) if !ann.nodes.is_empty() => {
+            let k = index_data.files.len().max(1).min(500);
+            let mut scores: HashMap<String, f32> = HashMap::new();
+            for (id, dist) in ann_index_search(ann, qe, k, None) {
+                let score = (1.0 - dist).max(0.0);
+                scores
+                    .entry(ann.paths[id].clone())
+                    .and_modify(|s| *s = (*s).max(score))
+                    .or_insert(score);
+            }
+            Some(scores)
         }
+        _ => None,
+    };
+
+    let query_lower = query.to_lowercase();
+    let mut candidates = Vec::with_capacity(index_data.files.len());
+
+    for file in &index_data.files {
+        let bm25 = *bm25_by_path.get(&file.path).unwrap_or(&0.0);
+        let name_score = token_overlap_score(&tokenize(&file.name), &query_terms);
+        let path_score = token_overlap_score(&tokenize(&file.path), &query_terms);
+
+        let vector = match &ann_vector_by_path {
+            Some(scores) => *scores.get(&file.path).unwrap_or(&0.0),
+            None => match (&query_embedding, chunks_by_path.get(&file.path)) {
+                (Some(qe), Some(chunks)) => chunks
+                    .iter()
+                    .map(|fe| 1.0 - cosine_distance(qe, &fe.embedding))
+                    .fold(f32::MIN, f32::max)
+                    .max(0.0),
+                _ => 0.0,
+            },
+        };
+
+        // Only worth a disk read for files that actually matched a signal;
+        // this is what lets a query skip reading every indexed file's
+        // content (the keyword score itself comes from `inverted.json`).
+        let preview = if bm25 > 0.0 || vector > 0.0 || name_score > 0.0 || path_score > 0.0 {
+            if is_image_extension(&file.extension) {
+                // Already computed at scan time - a BlurHash placeholder
+                // beats a text snippet for something that isn't prose.
+                file.blurhash.clone()
+            } else {
+                read_indexed_content(&file.path).ok().and_then(|content| {
+                    // Slice `content_lower` itself for the preview rather than
+                    // mapping `pos`/offsets back onto the original `content`:
+                    // `to_lowercase()` can change a string's byte length (e.g.
+                    // German ß -> ss, Turkish İ), so offsets found in one
+                    // string aren't guaranteed to land on char boundaries - or
+                    // even mean the same thing - in the other. `start`/`end`
+                    // are still arbitrary byte offsets once padded by the
+                    // surrounding window, so snap them to char boundaries the
+                    // same way `chunking::nearest_char_boundary` does.
+                    let content_lower = content.to_lowercase();
+                    content_lower.find(&query_lower).map(|pos| {
+                        let start = chunking::nearest_char_boundary(&content_lower, pos.saturating_sub(50));
+                        let end = chunking::nearest_char_boundary(
+                            &content_lower,
+                            (pos + query_lower.len() + 50).min(content_lower.len()),
+                        );
+                        content_lower[start..end].trim().to_string()
+                    })
+                })
+            }
+        } else {
+            None
+        };
+
+        candidates.push(RetrievalCandidate { file: file.clone(), name_score, path_score, bm25, vector, preview });
     }
 
-    // Sort by score descending and take top_k
+    Ok(candidates)
+}
+
+/// Hybrid keyword + vector search: blends a BM25 lexical score over tokenized
+/// file content with cosine similarity against chunk embeddings (when
+/// `embeddings.json` exists), so results stay useful before any embeddings
+/// have been generated (`ratio` near 0) and lean semantic as `ratio` rises.
+#[tauri::command]
+pub async fn hybrid_search(
+    query: String,
+    index_dir: String,
+    top_k: usize,
+    ratio: Option<f32>,
+) -> Result<serde_json::Value, String> {
+    let ratio = ratio.unwrap_or(0.5).clamp(0.0, 1.0);
+    let index_path = Path::new(&index_dir);
+    let index_data = load_index_data(index_path)?;
+    let candidates = dual_retrieve(index_path, &index_data, &query, None).await?;
+
+    let bm25_scores: Vec<f32> = candidates.iter().map(|c| c.bm25).collect();
+    let vector_scores: Vec<f32> = candidates.iter().map(|c| c.vector).collect();
+    let bm25_norm = min_max_normalize(&bm25_scores);
+    let vector_norm = min_max_normalize(&vector_scores);
+
+    let mut results: Vec<SearchResult> = candidates
+        .iter()
+        .zip(bm25_norm.iter())
+        .zip(vector_norm.iter())
+        .filter_map(|((candidate, lexical), vector)| {
+            let score = ratio * vector + (1.0 - ratio) * lexical;
+            if score <= 0.0 {
+                return None;
+            }
+            Some(SearchResult {
+                path: candidate.file.path.clone(),
+                name: candidate.file.name.clone(),
+                score,
+                preview: candidate.preview.clone(),
+            })
+        })
+        .collect();
+
     results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
     results.truncate(top_k);
 
@@ -1330,22 +2854,22 @@ pub async fn search(
 
 /// Get summary of clusters
 #[tauri::command]
-pub async fn get_clusters_summary(index_dir: String) -> Result<serde_json::Value, String> {
+pub async fn get_clusters_summary(index_dir: String) -> Result<serde_json::Value, ResponseError> {
     let index_path = Path::new(&index_dir);
     let clusters_file = index_path.join("clusters.json");
-    
+
     if !clusters_file.exists() {
         return Ok(serde_json::json!({
             "clusters": [],
             "message": "No clusters found. Please create clusters first."
         }));
     }
-    
+
     let content = fs::read_to_string(&clusters_file)
-        .map_err(|e| format!("Failed to read clusters file: {}", e))?;
-    
+        .map_err(|e| ResponseError::new("internal_error", format!("Failed to read clusters file: {}", e)))?;
+
     let clusters_data: ClustersData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse clusters: {}", e))?;
+        .map_err(|e| ResponseError::new("config_parse_error", format!("Failed to parse clusters: {}", e)))?;
     
     // Transform clusters for frontend display
     let clusters_summary: Vec<serde_json::Value> = clusters_data.clusters.iter().map(|cluster| {
@@ -1378,31 +2902,30 @@ pub async fn get_clusters_summary(index_dir: String) -> Result<serde_json::Value
 
 /// Get timeline of file modifications
 #[tauri::command]
-pub async fn get_timeline(index_dir: String, days: usize) -> Result<serde_json::Value, String> {
+pub async fn get_timeline(index_dir: String, days: usize, filter: Option<SearchFilter>) -> Result<serde_json::Value, String> {
     let index_path = Path::new(&index_dir);
     let index_file = index_path.join("index.json");
-    
+
     if !index_file.exists() {
         return Err("Index not found. Please scan a directory first.".to_string());
     }
-    
+
     let content = fs::read_to_string(&index_file)
         .map_err(|e| format!("Failed to read index: {}", e))?;
-    
-    let index_data: IndexData = serde_json::from_str(&content)
+
+    let mut index_data: IndexData = serde_json::from_str(&content)
         .map_err(|e| format!("Failed to parse index: {}", e))?;
-    
+    if let Some(filter) = &filter {
+        index_data.files.retain(|f| file_matches_filter(f, filter));
+    }
+
     // Group files by date
     let mut files_by_date: std::collections::HashMap<String, Vec<serde_json::Value>> = std::collections::HashMap::new();
-    
+
     for file in &index_data.files {
         // Parse the modified date and extract just the date part
-        let date_part = if file.modified.len() >= 10 {
-            file.modified[..10].to_string()
-        } else {
-            file.modified.clone()
-        };
-        
+        let date_part = modified_date_part(&file.modified).to_string();
+
         files_by_date
             .entry(date_part)
             .or_insert_with(Vec::new)
@@ -1458,10 +2981,16 @@ pub async fn get_stats(index_dir: String) -> Result<serde_json::Value, String> {
 
     let mut total_size: u64 = 0;
     let mut extensions: HashMap<String, usize> = HashMap::new();
+    let mut image_count: usize = 0;
+    let mut total_pixels: u64 = 0;
 
     for file in &index_data.files {
         total_size += file.size;
         *extensions.entry(file.extension.clone()).or_insert(0) += 1;
+        if let (Some(w), Some(h)) = (file.image_width, file.image_height) {
+            image_count += 1;
+            total_pixels += (w as u64) * (h as u64);
+        }
     }
 
     // Check embeddings
@@ -1503,7 +3032,11 @@ pub async fn get_stats(index_dir: String) -> Result<serde_json::Value, String> {
         "has_embeddings": has_embeddings,
         "embedding_count": embedding_count,
         "has_clusters": has_clusters,
-        "cluster_count": cluster_count
+        "cluster_count": cluster_count,
+        "media": {
+            "image_count": image_count,
+            "total_pixels": total_pixels
+        }
     }))
 }
 
@@ -1545,6 +3078,92 @@ pub async fn validate_index(index_dir: String) -> Result<serde_json::Value, Stri
     }
 }
 
+/// Report embedding index coverage against `index.json`: which indexed
+/// paths have no embedding at all, which embeddings point at paths that no
+/// longer read back (removed from disk or dropped from the index), and
+/// which are stale (the file's current content no longer matches any
+/// stored chunk hash). Lets the UI say "N files missing embeddings, M
+/// stale" and offer a targeted re-embed instead of a full re-run.
+#[tauri::command]
+pub async fn check_index_health(index_dir: String) -> Result<serde_json::Value, String> {
+    let index_path = Path::new(&index_dir);
+    let index_file = index_path.join("index.json");
+    let embeddings_file = index_path.join("embeddings.json");
+
+    if !index_file.exists() {
+        return Err("Index not found. Please scan a directory first.".to_string());
+    }
+
+    let index_content = fs::read_to_string(&index_file)
+        .map_err(|e| format!("Failed to read index: {}", e))?;
+    let index_data: IndexData = serde_json::from_str(&index_content)
+        .map_err(|e| format!("Failed to parse index: {}", e))?;
+
+    let indexed_paths: std::collections::HashSet<String> =
+        index_data.files.iter().map(|f| f.path.clone()).collect();
+
+    let embeddings: Vec<FileEmbedding> = if embeddings_file.exists() {
+        let raw = fs::read_to_string(&embeddings_file)
+            .map_err(|e| format!("Failed to read embeddings: {}", e))?;
+        serde_json::from_str::<EmbeddingsData>(&raw)
+            .map_err(|e| format!("Failed to parse embeddings: {}", e))?
+            .embeddings
+    } else {
+        Vec::new()
+    };
+
+    let mut by_path: HashMap<String, Vec<&FileEmbedding>> = HashMap::new();
+    for fe in &embeddings {
+        by_path.entry(fe.path.clone()).or_default().push(fe);
+    }
+
+    let mut missing_paths: Vec<String> = Vec::new();
+    let mut stale_paths: Vec<String> = Vec::new();
+
+    for path in &indexed_paths {
+        let Some(existing) = by_path.get(path) else {
+            missing_paths.push(path.clone());
+            continue;
+        };
+
+        let Ok(content) = read_indexed_content(path) else {
+            // Unreadable but still listed in index.json: its embeddings no
+            // longer read back, so it is reported as orphaned below.
+            continue;
+        };
+        let ext = chunking::extension_of(path);
+        let mut current_hashes: Vec<String> = chunking::chunk_text(&content, &ext)
+            .iter()
+            .map(|c| content_hash(&c.text))
+            .collect();
+        let mut stored_hashes: Vec<String> = existing.iter().map(|fe| fe.content_hash.clone()).collect();
+        current_hashes.sort();
+        stored_hashes.sort();
+        if current_hashes != stored_hashes {
+            stale_paths.push(path.clone());
+        }
+    }
+
+    // Orphaned: embeddings whose path is no longer in index.json at all, or
+    // whose source can no longer be read (file deleted/moved/row removed).
+    let orphaned_paths: Vec<String> = by_path
+        .keys()
+        .filter(|path| !indexed_paths.contains(*path) || read_indexed_content(path).is_err())
+        .cloned()
+        .collect();
+
+    Ok(serde_json::json!({
+        "total_indexed": indexed_paths.len(),
+        "total_embedded": by_path.len(),
+        "missing_count": missing_paths.len(),
+        "stale_count": stale_paths.len(),
+        "orphaned_count": orphaned_paths.len(),
+        "missing_paths": missing_paths,
+        "stale_paths": stale_paths,
+        "orphaned_paths": orphaned_paths,
+    }))
+}
+
 /// Get system information
 #[tauri::command]
 pub async fn get_system_info() -> Result<serde_json::Value, String> {
@@ -1570,37 +3189,33 @@ pub async fn save_azure_config(
         .map_err(|e| format!("Failed to create index directory: {}", e))?;
     
     let config_file = index_path.join("azure_config.json");
-    
-    // If no new key provided, try to preserve existing key
+
+    // If no new key provided, keep the one already in the keychain
     let final_api_key = if api_key.is_empty() {
-        // Try to load existing config to get the key
-        if config_file.exists() {
-            let content = fs::read_to_string(&config_file).ok();
-            content.and_then(|c| {
-                serde_json::from_str::<AzureConfig>(&c).ok()
-            }).map(|c| c.api_key).unwrap_or_default()
-        } else {
-            String::new()
-        }
+        load_secret(index_path, "azure", "api_key")
     } else {
         api_key
     };
-    
+
+    if !final_api_key.is_empty() {
+        store_secret(index_path, "azure", "api_key", &final_api_key)?;
+    }
+
     let config = AzureConfig {
         endpoint,
-        api_key: final_api_key,
+        api_key: String::new(),
         deployment_name,
         api_version: api_version.unwrap_or_else(|| "2024-02-01".to_string()),
     };
-    
+
     let json = serde_json::to_string_pretty(&config)
         .map_err(|e| format!("Failed to serialize config: {}", e))?;
-    
+
     fs::write(&config_file, json)
         .map_err(|e| format!("Failed to write config file: {}", e))?;
 
     // Ensure provider config is set to Azure when saving Azure config
-    let _ = write_provider_config(index_path, EmbeddingProvider::Azure, None);
+    let _ = write_provider_config(index_path, ProviderKind::Azure, None);
     
     Ok(serde_json::json!({
         "success": true,
@@ -1643,7 +3258,7 @@ pub async fn save_gcp_config(
         project_id,
         location,
         model_id,
-        service_account_path: final_sa_path,
+        service_account_path: final_sa_path.clone(),
         endpoint,
     };
 
@@ -1653,7 +3268,16 @@ pub async fn save_gcp_config(
     fs::write(&config_file, json)
         .map_err(|e| format!("Failed to write config file: {}", e))?;
 
-    let _ = write_provider_config(index_path, EmbeddingProvider::Gcp, None);
+    // Stash the service account JSON's contents in the keychain so the
+    // credential no longer has to live at a stable, world-readable path -
+    // `build_gcp_provider` prefers this over re-reading `final_sa_path`.
+    if !final_sa_path.trim().is_empty() {
+        if let Ok(contents) = fs::read_to_string(&final_sa_path) {
+            store_secret(index_path, "gcp", "service_account", &contents)?;
+        }
+    }
+
+    let _ = write_provider_config(index_path, ProviderKind::Gcp, None);
 
     Ok(serde_json::json!({
         "success": true,
@@ -1672,9 +3296,9 @@ pub async fn load_provider_config(index_dir: String) -> Result<serde_json::Value
     let config = resolve_provider_config(index_path);
     Ok(serde_json::json!({
         "provider": match config.provider {
-            EmbeddingProvider::Local => "local",
-            EmbeddingProvider::Azure => "azure",
-            EmbeddingProvider::Gcp => "gcp",
+            ProviderKind::Local => "local",
+            ProviderKind::Azure => "azure",
+            ProviderKind::Gcp => "gcp",
         },
         "local_model": config.local_model,
         "exists": index_path.join("provider_config.json").exists()
@@ -1694,16 +3318,16 @@ pub async fn save_provider_config(
     }
 
     let provider_enum = match provider.to_lowercase().as_str() {
-        "local" => EmbeddingProvider::Local,
-        "azure" => EmbeddingProvider::Azure,
-        "gcp" => EmbeddingProvider::Gcp,
+        "local" => ProviderKind::Local,
+        "azure" => ProviderKind::Azure,
+        "gcp" => ProviderKind::Gcp,
         _ => return Err("Unknown provider. Use 'local', 'azure', or 'gcp'.".to_string()),
     };
 
     let model = match provider_enum {
-        EmbeddingProvider::Local => Some(local_model.unwrap_or_else(default_local_model_name)),
-        EmbeddingProvider::Azure => local_model.clone(),
-        EmbeddingProvider::Gcp => local_model,
+        ProviderKind::Local => Some(local_model.unwrap_or_else(default_local_model_name)),
+        ProviderKind::Azure => local_model.clone(),
+        ProviderKind::Gcp => local_model,
     };
 
     write_provider_config(index_path, provider_enum, model)?;
@@ -1734,13 +3358,15 @@ pub async fn load_azure_config(index_dir: String) -> Result<serde_json::Value, S
     
     let config: AzureConfig = serde_json::from_str(&content)
         .map_err(|e| format!("Failed to parse config: {}", e))?;
-    
+
+    let has_key = !config.api_key.is_empty() || !load_secret(index_path, "azure", "api_key").is_empty();
+
     Ok(serde_json::json!({
-        "configured": !config.api_key.is_empty(),
+        "configured": has_key,
         "endpoint": config.endpoint,
         "deployment_name": config.deployment_name,
         "api_version": config.api_version,
-        "has_key": !config.api_key.is_empty()
+        "has_key": has_key
     }))
 }
 
@@ -1766,27 +3392,38 @@ pub async fn load_gcp_config(index_dir: String) -> Result<serde_json::Value, Str
     let config: GcpConfig = serde_json::from_str(&content)
         .map_err(|e| format!("Failed to parse config: {}", e))?;
 
+    let has_key = !config.service_account_path.is_empty()
+        || !load_secret(index_path, "gcp", "service_account").is_empty();
+
     Ok(serde_json::json!({
-        "configured": !config.service_account_path.is_empty(),
+        "configured": has_key,
         "project_id": config.project_id,
         "location": config.location,
         "model_id": config.model_id,
         "endpoint": config.endpoint,
-        "has_key": !config.service_account_path.is_empty()
+        "has_key": has_key
     }))
 }
 
 /// Validate Azure configuration by making a small embeddings request
 #[tauri::command]
 pub async fn validate_azure_config(
-    _index_dir: String,
+    index_dir: String,
     endpoint: String,
     api_key: String,
     deployment_name: String,
     api_version: Option<String>,
-) -> Result<serde_json::Value, String> {
+) -> Result<serde_json::Value, ResponseError> {
     println!("[RUST] validate_azure_config called for endpoint: {}", endpoint);
 
+    // Let callers re-validate an already-saved config without having to
+    // retype the key - pull it from the keychain when none is supplied.
+    let api_key = if api_key.is_empty() {
+        load_secret(Path::new(&index_dir), "azure", "api_key")
+    } else {
+        api_key
+    };
+
     // Normalize endpoint. Keep original for suggestion heuristics.
     let orig = endpoint.trim_end_matches('/').to_string();
     let mut base = orig.clone();
@@ -1831,7 +3468,7 @@ pub async fn validate_azure_config(
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(8))
         .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        .map_err(|e| ResponseError::new("network_error", format!("Failed to create HTTP client: {}", e)))?;
 
     // Try current and fallbacks
     let mut last_url: Option<String> = None;
@@ -1872,103 +3509,75 @@ pub async fn validate_azure_config(
                         continue;
                     }
                     // Return error details
-                    return Ok(serde_json::json!({
-                        "success": false,
-                        "message": format!("Server returned {}: {}", status, text),
-                        "tried_versions": tried_versions,
-                        "final_url": url,
-                        "status_code": status,
-                        "suggested_endpoint": suggested
-                    }));
+                    let code = if status == 401 || status == 403 { "azure_auth_failed" } else { "azure_validation_failed" };
+                    let mut message = format!("Server returned {}: {} (tried versions: {})", status, text, tried_versions.join(", "));
+                    if let Some(ref s) = suggested {
+                        message = format!("{}; suggested endpoint: {}", message, s);
+                    }
+                    return Err(ResponseError::new(code, message).with_status(status));
                 }
             }
             Err(e) => {
                 println!("[RUST] Request error: {}", e);
                 // network or connection error - return as failure but include suggestion
-                return Ok(serde_json::json!({
-                    "success": false,
-                    "message": format!("Request failed: {}", e),
-                    "tried_versions": tried_versions,
-                    "final_url": last_url,
-                    "suggested_endpoint": suggested
-                }));
+                let mut message = format!("Request failed: {} (tried versions: {})", e, tried_versions.join(", "));
+                if let Some(ref s) = suggested {
+                    message = format!("{}; suggested endpoint: {}", message, s);
+                }
+                return Err(ResponseError::new("network_error", message));
             }
         }
     }
 
-    Ok(serde_json::json!({
-        "success": false,
-        "message": "All tried API versions failed",
-        "tried_versions": tried_versions,
-        "final_url": last_url,
-        "suggested_endpoint": suggested
-    }))
+    Err(ResponseError::new("azure_validation_failed", format!("All tried API versions failed: {}", tried_versions.join(", "))))
 }
 
 /// Validate Google Cloud configuration
 #[tauri::command]
 pub async fn validate_gcp_config(
+    index_dir: String,
     project_id: String,
     location: String,
     model_id: String,
     service_account_path: String,
     endpoint: Option<String>,
-) -> Result<serde_json::Value, String> {
-    if service_account_path.trim().is_empty() {
-        return Ok(serde_json::json!({
-            "success": false,
-            "message": "Service account JSON path is required"
-        }));
-    }
-
-    let sa_path = std::path::Path::new(&service_account_path);
-    if !sa_path.exists() {
-        return Ok(serde_json::json!({
-            "success": false,
-            "message": format!("Service account file not found: {}", service_account_path)
-        }));
-    }
-
-    let key = match yup_oauth2::read_service_account_key(sa_path).await {
-        Ok(k) => k,
-        Err(e) => {
-            return Ok(serde_json::json!({
-                "success": false,
-                "message": format!("Failed to read service account: {}", e)
-            }))
+) -> Result<serde_json::Value, ResponseError> {
+    // Like `build_gcp_provider`, prefer the service account JSON already in
+    // the keychain; `service_account_path` is only read when none is stored.
+    let stored_json = load_secret(Path::new(&index_dir), "gcp", "service_account");
+
+    let key = if !stored_json.trim().is_empty() {
+        yup_oauth2::parse_service_account_key(&stored_json)
+            .map_err(|e| ResponseError::new("config_parse_error", format!("Failed to parse stored service account: {}", e)))?
+    } else {
+        if service_account_path.trim().is_empty() {
+            return Err(ResponseError::new("config_incomplete", "Service account JSON path is required"));
         }
-    };
 
-    let auth = match yup_oauth2::ServiceAccountAuthenticator::builder(key).build().await {
-        Ok(a) => a,
-        Err(e) => {
-            return Ok(serde_json::json!({
-                "success": false,
-                "message": format!("Failed to build authenticator: {}", e)
-            }))
+        let sa_path = std::path::Path::new(&service_account_path);
+        if !sa_path.exists() {
+            return Err(ResponseError::new("path_not_found", format!("Service account file not found: {}", service_account_path)));
         }
-    };
 
-    let token_res = auth.token(&["https://www.googleapis.com/auth/cloud-platform"]).await;
-    let token = match token_res {
-        Ok(t) => t,
-        Err(e) => {
-            return Ok(serde_json::json!({
-                "success": false,
-                "message": format!("Failed to fetch token: {}", e)
-            }))
-        }
+        yup_oauth2::read_service_account_key(sa_path)
+            .await
+            .map_err(|e| ResponseError::new("config_parse_error", format!("Failed to read service account: {}", e)))?
     };
 
-    let bearer = match token.token() {
-        Some(t) => t.to_string(),
-        None => {
-            return Ok(serde_json::json!({
-                "success": false,
-                "message": "Token missing access token"
-            }))
-        }
-    };
+    let auth = yup_oauth2::ServiceAccountAuthenticator::builder(key)
+        .build()
+        .await
+        .map_err(|e| ResponseError::new("gcp_auth_failed", format!("Failed to build authenticator: {}", e)))?;
+
+    let token = auth
+        .token(&["https://www.googleapis.com/auth/cloud-platform"])
+        .await
+        .map_err(|e| ResponseError::new("gcp_auth_failed", format!("Failed to fetch token: {}", e)))?;
+
+    let bearer = token
+        .token()
+        .ok_or_else(|| ResponseError::new("gcp_auth_failed", "Token missing access token"))?
+        .to_string();
 
     let url = endpoint.unwrap_or_else(|| format!(
         "https://{}-aiplatform.googleapis.com/v1/projects/{}/locations/{}/publishers/google/models/{}:predict",
@@ -1987,120 +3596,198 @@ pub async fn validate_gcp_config(
             ]
         }))
         .send()
-        .await;
-
-    match response {
-        Ok(res) => {
-            if res.status().is_success() {
-                Ok(serde_json::json!({
-                    "success": true,
-                    "message": "GCP validation successful"
-                }))
-            } else {
-                let status = res.status();
-                let text = res.text().await.unwrap_or_default();
-                Ok(serde_json::json!({
-                    "success": false,
-                    "message": format!("GCP validation failed with status {}: {}", status, text)
-                }))
+        .await
+        .map_err(|e| ResponseError::new("network_error", format!("GCP validation request failed: {}", e)))?;
+
+    if response.status().is_success() {
+        Ok(serde_json::json!({
+            "success": true,
+            "message": "GCP validation successful"
+        }))
+    } else {
+        let status = response.status().as_u16();
+        let text = response.text().await.unwrap_or_default();
+        let code = if status == 401 || status == 403 { "gcp_auth_failed" } else { "gcp_validation_failed" };
+        Err(ResponseError::new(code, format!("GCP validation failed with status {}: {}", status, text)).with_status(status))
+    }
+}
+
+/// Max number of config validations allowed in flight at once when sweeping
+/// a tree of `.wayfinder_index` directories, so a repo with dozens of
+/// indexes doesn't fire dozens of simultaneous outbound requests.
+const VALIDATE_SWEEP_CONCURRENCY: usize = 8;
+
+/// Find every `.wayfinder_index` directory under `root_path`, in walk order.
+fn find_index_dirs(root_path: &str) -> Vec<String> {
+    WalkDir::new(root_path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_dir() && entry.file_name() == ".wayfinder_index")
+        .map(|entry| entry.path().to_string_lossy().to_string())
+        .collect()
+}
+
+/// Validate one index directory's `azure_config.json`, if present.
+async fn validate_one_azure_index(idx_dir: String) -> serde_json::Value {
+    let cfg_file = Path::new(&idx_dir).join("azure_config.json");
+    if !cfg_file.exists() {
+        // No Azure config present; mark as skipped instead of failure so GCP-only indexes don't look broken
+        return serde_json::json!({
+            "index_dir": idx_dir,
+            "config": null,
+            "validation": {"success": true, "message": "No azure_config.json present (skipped)"}
+        });
+    }
+
+    match fs::read_to_string(&cfg_file) {
+        Ok(content) => match serde_json::from_str::<AzureConfig>(&content) {
+            Ok(cfg) => {
+                // Call existing validate function to reuse logic
+                match validate_azure_config(idx_dir.clone(), cfg.endpoint.clone(), cfg.api_key.clone(), cfg.deployment_name.clone(), Some(cfg.api_version.clone())).await {
+                    Ok(v) => serde_json::json!({ "index_dir": idx_dir, "config": cfg, "validation": v }),
+                    Err(e) => serde_json::json!({ "index_dir": idx_dir, "config": cfg, "error": e }),
+                }
             }
-        }
-        Err(e) => Ok(serde_json::json!({
-            "success": false,
-            "message": format!("GCP validation request failed: {}", e)
-        })),
+            Err(e) => serde_json::json!({ "index_dir": idx_dir, "error": format!("Failed to parse config: {}", e) }),
+        },
+        Err(e) => serde_json::json!({ "index_dir": idx_dir, "error": format!("Failed to read config: {}", e) }),
+    }
+}
+
+/// Validate one index directory's `gcp_config.json`, if present.
+async fn validate_one_gcp_index(idx_dir: String) -> serde_json::Value {
+    let cfg_file = Path::new(&idx_dir).join("gcp_config.json");
+    if !cfg_file.exists() {
+        return serde_json::json!({
+            "index_dir": idx_dir,
+            "config": null,
+            "validation": {"success": true, "message": "No gcp_config.json present (skipped)"}
+        });
+    }
+
+    match fs::read_to_string(&cfg_file) {
+        Ok(content) => match serde_json::from_str::<GcpConfig>(&content) {
+            Ok(cfg) => {
+                match validate_gcp_config(idx_dir.clone(), cfg.project_id.clone(), cfg.location.clone(), cfg.model_id.clone(), cfg.service_account_path.clone(), cfg.endpoint.clone()).await {
+                    Ok(v) => serde_json::json!({ "index_dir": idx_dir, "config": cfg, "validation": v }),
+                    Err(e) => serde_json::json!({ "index_dir": idx_dir, "config": cfg, "error": e }),
+                }
+            }
+            Err(e) => serde_json::json!({ "index_dir": idx_dir, "error": format!("Failed to parse config: {}", e) }),
+        },
+        Err(e) => serde_json::json!({ "index_dir": idx_dir, "error": format!("Failed to read config: {}", e) }),
+    }
+}
+
+/// Run `validator` over `idx_dirs` with at most `VALIDATE_SWEEP_CONCURRENCY`
+/// in flight at once, returning results in the same order as `idx_dirs`.
+async fn run_bounded_sweep<F, Fut>(idx_dirs: Vec<String>, validator: F) -> Vec<serde_json::Value>
+where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = serde_json::Value> + Send + 'static,
+{
+    let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(VALIDATE_SWEEP_CONCURRENCY));
+    let handles: Vec<_> = idx_dirs
+        .into_iter()
+        .map(|idx_dir| {
+            let semaphore = semaphore.clone();
+            let fut = validator(idx_dir);
+            tokio::spawn(async move {
+                let _permit = semaphore.acquire_owned().await.expect("semaphore closed");
+                fut.await
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(handle.await.unwrap_or_else(|e| {
+            serde_json::json!({ "error": format!("Validation task panicked: {}", e) })
+        }));
     }
+    results
 }
 
-/// Validate azure_config.json files found under a root path (recursively)
+/// Validate azure_config.json files found under a root path (recursively).
+/// Candidate index directories are validated concurrently, bounded by
+/// `VALIDATE_SWEEP_CONCURRENCY`, while results are returned in the same
+/// order the directories were discovered.
 #[tauri::command]
-pub async fn validate_all_azure_configs(root_path: String) -> Result<serde_json::Value, String> {
+pub async fn validate_all_azure_configs(root_path: String) -> Result<serde_json::Value, ResponseError> {
     println!("[RUST] validate_all_azure_configs scanning: {}", root_path);
 
-    let mut results: Vec<serde_json::Value> = Vec::new();
-
     if !Path::new(&root_path).exists() {
-        return Err(format!("Root path does not exist: {}", root_path));
-    }
-
-    for entry in WalkDir::new(&root_path).into_iter().filter_map(|e| e.ok()) {
-        if entry.file_type().is_dir() && entry.file_name() == ".wayfinder_index" {
-            let idx_dir = entry.path().to_string_lossy().to_string();
-            let cfg_file = entry.path().join("azure_config.json");
-            if cfg_file.exists() {
-                match fs::read_to_string(&cfg_file) {
-                    Ok(content) => {
-                        match serde_json::from_str::<AzureConfig>(&content) {
-                            Ok(cfg) => {
-                                // Call existing validate function to reuse logic
-                                match validate_azure_config(idx_dir.clone(), cfg.endpoint.clone(), cfg.api_key.clone(), cfg.deployment_name.clone(), Some(cfg.api_version.clone())).await {
-                                    Ok(v) => {
-                                        results.push(serde_json::json!({
-                                            "index_dir": idx_dir,
-                                            "config": cfg,
-                                            "validation": v
-                                        }));
-                                    }
-                                    Err(e) => {
-                                        results.push(serde_json::json!({
-                                            "index_dir": idx_dir,
-                                            "config": cfg,
-                                            "error": e
-                                        }));
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                results.push(serde_json::json!({
-                                    "index_dir": idx_dir,
-                                    "error": format!("Failed to parse config: {}", e)
-                                }));
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        results.push(serde_json::json!({
-                            "index_dir": idx_dir,
-                            "error": format!("Failed to read config: {}", e)
-                        }));
-                    }
-                }
-            } else {
-                // No Azure config present; mark as skipped instead of failure so GCP-only indexes don't look broken
-                results.push(serde_json::json!({
-                    "index_dir": idx_dir,
-                    "config": null,
-                    "validation": {"success": true, "message": "No azure_config.json present (skipped)"}
-                }));
-            }
-        }
+        return Err(ResponseError::new("path_not_found", format!("Root path does not exist: {}", root_path)));
     }
 
+    let idx_dirs = find_index_dirs(&root_path);
+    let results = run_bounded_sweep(idx_dirs, |idx_dir| validate_one_azure_index(idx_dir)).await;
+
     Ok(serde_json::json!({
         "success": true,
         "root_scanned": root_path,
+        "total": results.len(),
         "results": results
     }))
 }
 
+/// Sibling of `validate_all_azure_configs` that sweeps the same tree for
+/// `gcp_config.json` files and validates each via the service-account/token
+/// flow, so one call reports Azure and GCP index health together.
+#[tauri::command]
+pub async fn validate_all_cloud_configs(root_path: String) -> Result<serde_json::Value, ResponseError> {
+    println!("[RUST] validate_all_cloud_configs scanning: {}", root_path);
+
+    if !Path::new(&root_path).exists() {
+        return Err(ResponseError::new("path_not_found", format!("Root path does not exist: {}", root_path)));
+    }
+
+    let idx_dirs = find_index_dirs(&root_path);
+    let azure_results = run_bounded_sweep(idx_dirs.clone(), |idx_dir| validate_one_azure_index(idx_dir)).await;
+    let gcp_results = run_bounded_sweep(idx_dirs.clone(), |idx_dir| validate_one_gcp_index(idx_dir)).await;
+
+    let healthy_count = |results: &[serde_json::Value]| {
+        results
+            .iter()
+            .filter(|r| r.pointer("/validation/success").and_then(|v| v.as_bool()).unwrap_or(false))
+            .count()
+    };
+
+    Ok(serde_json::json!({
+        "success": true,
+        "root_scanned": root_path,
+        "total_indexes": idx_dirs.len(),
+        "azure": {
+            "validated": azure_results.len(),
+            "healthy": healthy_count(&azure_results),
+            "results": azure_results
+        },
+        "gcp": {
+            "validated": gcp_results.len(),
+            "healthy": healthy_count(&gcp_results),
+            "results": gcp_results
+        }
+    }))
+}
+
 /// Get clusters summary for display
 #[tauri::command]
-pub async fn get_clusters_data(index_dir: String) -> Result<serde_json::Value, String> {
+pub async fn get_clusters_data(index_dir: String) -> Result<serde_json::Value, ResponseError> {
     let index_path = Path::new(&index_dir);
     let clusters_file = index_path.join("clusters.json");
-    
+
     if !clusters_file.exists() {
         return Ok(serde_json::json!({
             "has_clusters": false,
             "clusters": []
         }));
     }
-    
+
     let content = fs::read_to_string(&clusters_file)
-        .map_err(|e| format!("Failed to read clusters: {}", e))?;
-    
+        .map_err(|e| ResponseError::new("internal_error", format!("Failed to read clusters: {}", e)))?;
+
     let clusters_data: ClustersData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse clusters: {}", e))?;
+        .map_err(|e| ResponseError::new("config_parse_error", format!("Failed to parse clusters: {}", e)))?;
     
     // Return cluster summaries (without full centroids for UI)
     let clusters_summary: Vec<serde_json::Value> = clusters_data.clusters.iter().map(|c| {
@@ -2121,9 +3808,9 @@ pub async fn get_clusters_data(index_dir: String) -> Result<serde_json::Value, S
 
 /// Get Git Clippy report for a repository
 #[tauri::command]
-pub async fn get_git_clippy_report(repo_path: String, index_dir: Option<String>) -> Result<serde_json::Value, String> {
+pub async fn get_git_clippy_report(repo_path: String, index_dir: Option<String>) -> Result<serde_json::Value, ResponseError> {
     println!("[RUST] get_git_clippy_report called for: {}", repo_path);
-    
+
     // Load index data if available
     let index_files = if let Some(ref dir) = index_dir {
         let index_file = Path::new(dir).join("index.json");
@@ -2137,24 +3824,26 @@ pub async fn get_git_clippy_report(repo_path: String, index_dir: Option<String>)
     } else {
         None
     };
-    
-    let report = git_assistant::generate_clippy_report(&repo_path, index_files.as_deref())?;
-    
+
+    let report = git_assistant::generate_clippy_report(&repo_path, index_files.as_deref())
+        .map_err(|e| ResponseError::new("git_error", e))?;
+
     serde_json::to_value(report)
-        .map_err(|e| format!("Failed to serialize report: {}", e))
+        .map_err(|e| ResponseError::new("internal_error", format!("Failed to serialize report: {}", e)))
 }
 
 /// Execute a Git Clippy action
 #[tauri::command]
 pub async fn execute_clippy_action(
-    repo_path: String, 
-    action: String, 
+    repo_path: String,
+    action: String,
     data: Option<serde_json::Value>
-) -> Result<serde_json::Value, String> {
+) -> Result<serde_json::Value, ResponseError> {
     println!("[RUST] execute_clippy_action: {} for {}", action, repo_path);
-    
-    let result = git_assistant::execute_git_action(&repo_path, &action, data.as_ref())?;
-    
+
+    let result = git_assistant::execute_git_action(&repo_path, &action, data.as_ref())
+        .map_err(|e| ResponseError::new("git_error", e))?;
+
     Ok(serde_json::json!({
         "success": true,
         "output": result
@@ -2206,24 +3895,40 @@ use crate::file_intelligence::{
 use std::sync::Mutex;
 use once_cell::sync::Lazy;
 
-// Global state for user preferences (will be replaced with SQLite later)
-static USER_PREFS: Lazy<Mutex<UserPreferences>> = Lazy::new(|| Mutex::new(UserPreferences::default()));
-static LAST_SCAN: Lazy<Mutex<Vec<DiscoveredDocument>>> = Lazy::new(|| Mutex::new(Vec::new()));
+// Durable state (preferences/dismissals, last scan), behind the `Store`
+// trait (`store.rs`) so a restart doesn't lose either one. `CURRENT_SCAN_ROOT`
+// remembers which root key the commands below should read/write, since
+// `get_organization_suggestions`/`get_scan_statistics`/`dismiss_suggestion`
+// take no root path of their own and exist to operate on "whatever was last
+// scanned".
+static STORE: Lazy<Box<dyn Store>> = Lazy::new(store::build_default_store);
+static CURRENT_SCAN_ROOT: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+const DEFAULT_SCAN_ROOT_KEY: &str = "default";
+
+fn current_scan_root_key() -> String {
+    CURRENT_SCAN_ROOT.lock().ok().and_then(|g| g.clone()).unwrap_or_else(|| DEFAULT_SCAN_ROOT_KEY.to_string())
+}
 
 /// Scan a directory for organizable documents
 #[tauri::command]
-pub async fn scan_for_documents(root_path: String, max_depth: Option<usize>) -> Result<serde_json::Value, String> {
+pub async fn scan_for_documents(root_path: String, max_depth: Option<usize>) -> Result<serde_json::Value, ResponseError> {
     println!("[FILE_INTEL] scan_for_documents: {}", root_path);
-    
-    let documents = file_intelligence::scan_for_documents(&root_path, max_depth)?;
-    
-    // Store for later use
-    if let Ok(mut scan) = LAST_SCAN.lock() {
-        *scan = documents.clone();
+
+    let documents = file_intelligence::scan_for_documents(&root_path, max_depth)
+        .map_err(|e| ResponseError::new("internal_error", e))?;
+
+    // Remember this as the active root, then persist the scan under it so a
+    // restart doesn't force a full re-scan before suggestions work again.
+    if let Ok(mut root) = CURRENT_SCAN_ROOT.lock() {
+        *root = Some(root_path.clone());
     }
-    
+    if let Err(e) = STORE.save_last_scan(&root_path, &documents) {
+        eprintln!("[STORE] Failed to persist scan for {}: {}", root_path, e);
+    }
+
     let count = documents.len();
-    
+
     Ok(serde_json::json!({
         "success": true,
         "document_count": count,
@@ -2233,17 +3938,13 @@ pub async fn scan_for_documents(root_path: String, max_depth: Option<usize>) ->
 
 /// Get organization suggestions based on last scan
 #[tauri::command]
-pub async fn get_organization_suggestions() -> Result<serde_json::Value, String> {
+pub async fn get_organization_suggestions() -> Result<serde_json::Value, ResponseError> {
     println!("[FILE_INTEL] get_organization_suggestions");
-    
-    let documents = LAST_SCAN.lock()
-        .map_err(|e| format!("Lock error: {}", e))?
-        .clone();
-    
-    let prefs = USER_PREFS.lock()
-        .map_err(|e| format!("Lock error: {}", e))?
-        .clone();
-    
+
+    let root_key = current_scan_root_key();
+    let documents = STORE.get_last_scan(&root_key);
+    let prefs = STORE.get_preferences(&root_key);
+
     if documents.is_empty() {
         return Ok(serde_json::json!({
             "success": true,
@@ -2253,23 +3954,52 @@ pub async fn get_organization_suggestions() -> Result<serde_json::Value, String>
     }
     
     let suggestions = file_intelligence::generate_suggestions(&documents, &prefs);
-    
+
+    // Semantic neighbor suggestions ("put this near files it's semantically
+    // related to"), sourced from `embed_file_intelligence_documents`'s vector
+    // index when one exists. Surfaced alongside the heuristic suggestions
+    // rather than folded into `OrganizationSuggestion` itself, since that
+    // type and its suggestion kinds belong to `file_intelligence`.
+    let doc_embeddings = load_file_intel_embeddings();
+    let semantic_neighbors: Vec<serde_json::Value> = documents
+        .iter()
+        .filter_map(|doc| {
+            let query = doc_embeddings.iter().find(|e| e.path == doc.path)?;
+            let mut ranked: Vec<(String, f32)> = doc_embeddings
+                .iter()
+                .filter(|e| e.path != doc.path)
+                .map(|e| (e.path.clone(), 1.0 - cosine_distance(&query.embedding, &e.embedding)))
+                .collect();
+            ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            ranked.truncate(3);
+            if ranked.is_empty() {
+                return None;
+            }
+            Some(serde_json::json!({
+                "path": doc.path,
+                "related_paths": ranked.iter().map(|(path, similarity)| serde_json::json!({
+                    "path": path,
+                    "similarity": similarity
+                })).collect::<Vec<_>>()
+            }))
+        })
+        .collect();
+
     Ok(serde_json::json!({
         "success": true,
         "suggestion_count": suggestions.len(),
-        "suggestions": suggestions
+        "suggestions": suggestions,
+        "semantic_neighbors": semantic_neighbors
     }))
 }
 
 /// Get statistics about the scanned documents
 #[tauri::command]
-pub async fn get_scan_statistics() -> Result<serde_json::Value, String> {
+pub async fn get_scan_statistics() -> Result<serde_json::Value, ResponseError> {
     println!("[FILE_INTEL] get_scan_statistics");
-    
-    let documents = LAST_SCAN.lock()
-        .map_err(|e| format!("Lock error: {}", e))?
-        .clone();
-    
+
+    let documents = STORE.get_last_scan(&current_scan_root_key());
+
     if documents.is_empty() {
         return Ok(serde_json::json!({
             "success": false,
@@ -2289,30 +4019,227 @@ pub async fn get_scan_statistics() -> Result<serde_json::Value, String> {
 
 /// Dismiss a suggestion (don't suggest this file again)
 #[tauri::command]
-pub async fn dismiss_suggestion(file_path: String) -> Result<serde_json::Value, String> {
+pub async fn dismiss_suggestion(file_path: String) -> Result<serde_json::Value, ResponseError> {
     println!("[FILE_INTEL] dismiss_suggestion: {}", file_path);
-    
-    let mut prefs = USER_PREFS.lock()
-        .map_err(|e| format!("Lock error: {}", e))?;
-    
-    prefs.dismissed_suggestions.push(file_path.clone());
-    
+
+    STORE
+        .dismiss_suggestion(&current_scan_root_key(), &file_path)
+        .map_err(|e| ResponseError::new("internal_error", e))?;
+
     Ok(serde_json::json!({
         "success": true,
         "dismissed": file_path
     }))
 }
 
-// ============================================================================
-// FILE WATCHER COMMANDS
-// ============================================================================
+// ----------------------------------------------------------------------------
+// Semantic similarity layer
+// ----------------------------------------------------------------------------
+// `scan_for_documents`/`generate_suggestions`/`detect_naming_patterns` are
+// purely lexical/heuristic. This adds a vector index alongside them: each
+// document's text is chunked the same way the main index chunks files
+// (`chunking::chunk_text`), embedded with the Azure or GCP provider when
+// `index_dir` has one configured (reusing `build_azure_provider`/
+// `build_gcp_provider`), or with `deterministic_embedding` when the provider
+// is Local (also the default when none is configured; Ollama is not called
+// here), then mean-pooled into one vector per path so lookups don't need to
+// know how a document was chunked.
 
-use crate::file_watcher::{FileWatcher, WatchConfig, FileEvent};
-use crate::file_watcher::event_to_document;
-use crate::file_watcher::should_prompt_for_event;
-use crate::file_watcher::SavePrompterConfig;
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DocumentEmbedding {
+    pub path: String,
+    pub content_hash: String,
+    pub embedding: Vec<f32>,
+}
 
-static FILE_WATCHER: Lazy<Mutex<Option<FileWatcher>>> = Lazy::new(|| Mutex::new(None));
+fn file_intel_embeddings_path() -> std::path::PathBuf {
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .unwrap_or_else(|_| ".".to_string());
+    Path::new(&home).join(".wayfinder").join("file_intel_embeddings.json")
+}
+
+fn load_file_intel_embeddings() -> Vec<DocumentEmbedding> {
+    fs::read_to_string(file_intel_embeddings_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_file_intel_embeddings(embeddings: &[DocumentEmbedding]) -> Result<(), String> {
+    let path = file_intel_embeddings_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(embeddings)
+        .map_err(|e| format!("Failed to serialize file intelligence embeddings: {}", e))?;
+    fs::write(path, json).map_err(|e| format!("Failed to write file intelligence embeddings: {}", e))
+}
+
+fn mean_pool(vectors: &[Vec<f32>]) -> Vec<f32> {
+    if vectors.is_empty() {
+        return Vec::new();
+    }
+    let dim = vectors[0].len();
+    let mut out = vec![0.0f32; dim];
+    for v in vectors {
+        for i in 0..dim.min(v.len()) {
+            out[i] += v[i];
+        }
+    }
+    let n = vectors.len() as f32;
+    for x in out.iter_mut() {
+        *x /= n;
+    }
+    out
+}
+
+/// Chunk `text`, embed each chunk with `index_path`'s configured provider,
+/// and mean-pool the results into a single document-level vector.
+async fn embed_document_text(index_path: &Path, doc_path: &str, text: &str) -> Result<Vec<f32>, String> {
+    let provider_config = resolve_provider_config(index_path);
+    let extension = chunking::extension_of(doc_path);
+    let chunks = chunking::chunk_text(text, &extension);
+    let inputs: Vec<String> = if chunks.is_empty() {
+        vec![format!("passage: {}", text)]
+    } else {
+        chunks.iter().map(|c| format!("passage: {}", c.text)).collect()
+    };
+
+    let vectors: Vec<Vec<f32>> = match provider_config.provider {
+        ProviderKind::Azure => {
+            let provider = build_azure_provider(index_path)?;
+            provider.embed_batch(&inputs).await.map_err(|e| e.message)?
+        }
+        ProviderKind::Gcp => {
+            let provider = build_gcp_provider(index_path).await?;
+            provider.embed_batch(&inputs).await.map_err(|e| e.message)?
+        }
+        ProviderKind::Local => inputs.iter().map(|t| deterministic_embedding(t, 512)).collect(),
+    };
+    Ok(mean_pool(&vectors))
+}
+
+/// Re-embed just the document an edit touched, so `find_similar_documents`
+/// stays fresh without waiting for a full `embed_file_intelligence_documents`
+/// pass. Goes through the same provider-aware `embed_document_text` that
+/// pass does, via the tokio handle `ensure_job_workers_started` captured,
+/// so a watcher-triggered re-embed doesn't silently overwrite a real
+/// Azure/GCP vector with an incompatible deterministic one.
+fn reembed_document_incremental(doc: &DiscoveredDocument) {
+    let hash = content_hash(&doc.content);
+    let mut embeddings = load_file_intel_embeddings();
+    if embeddings.iter().any(|e| e.path == doc.path && e.content_hash == hash) {
+        return;
+    }
+    let index_path = Path::new(&current_scan_root_key()).to_path_buf();
+    let handle = JOB_TOKIO_HANDLE.lock().ok().and_then(|h| h.clone());
+    let result = match handle {
+        Some(handle) => handle.block_on(embed_document_text(&index_path, &doc.path, &doc.content)),
+        None => Err("No tokio runtime handle available for re-embedding".to_string()),
+    };
+    match result {
+        Ok(pooled) => {
+            embeddings.retain(|e| e.path != doc.path);
+            embeddings.push(DocumentEmbedding { path: doc.path.clone(), content_hash: hash, embedding: pooled });
+            let _ = save_file_intel_embeddings(&embeddings);
+        }
+        Err(e) => {
+            log_error(&index_path, "reembed_document_incremental", Some(&doc.path), &e, None);
+        }
+    }
+}
+
+/// Scan `root_path` and embed every discovered document, skipping any whose
+/// content hash hasn't changed since the last run.
+#[tauri::command]
+pub async fn embed_file_intelligence_documents(index_dir: String, root_path: String, max_files: Option<usize>) -> Result<serde_json::Value, ResponseError> {
+    let index_path = Path::new(&index_dir);
+    let documents = file_intelligence::scan_for_documents(&root_path, None)
+        .map_err(|e| ResponseError::new("internal_error", e))?;
+    let documents: Vec<DiscoveredDocument> = match max_files {
+        Some(max) => documents.into_iter().take(max).collect(),
+        None => documents,
+    };
+
+    let mut by_path: HashMap<String, DocumentEmbedding> =
+        load_file_intel_embeddings().into_iter().map(|e| (e.path.clone(), e)).collect();
+
+    let mut embedded_count = 0usize;
+    let mut skipped_count = 0usize;
+    let mut error_count = 0usize;
+
+    for doc in &documents {
+        let hash = content_hash(&doc.content);
+        if by_path.get(&doc.path).map(|e| e.content_hash == hash).unwrap_or(false) {
+            skipped_count += 1;
+            continue;
+        }
+        match embed_document_text(index_path, &doc.path, &doc.content).await {
+            Ok(vector) => {
+                by_path.insert(doc.path.clone(), DocumentEmbedding { path: doc.path.clone(), content_hash: hash, embedding: vector });
+                embedded_count += 1;
+            }
+            Err(e) => {
+                log_error(index_path, "file_intel_embedding", Some(&doc.path), &e, None);
+                error_count += 1;
+            }
+        }
+    }
+
+    let embeddings: Vec<DocumentEmbedding> = by_path.into_values().collect();
+    save_file_intel_embeddings(&embeddings).map_err(|e| ResponseError::new("internal_error", e))?;
+
+    Ok(serde_json::json!({
+        "success": true,
+        "documents_scanned": documents.len(),
+        "embedded": embedded_count,
+        "skipped_unchanged": skipped_count,
+        "errors": error_count
+    }))
+}
+
+/// Nearest neighbors of `file_path` by cosine similarity over the persisted
+/// document vector index.
+#[tauri::command]
+pub async fn find_similar_documents(file_path: String, top_k: Option<usize>) -> Result<serde_json::Value, ResponseError> {
+    let embeddings = load_file_intel_embeddings();
+    let query = embeddings
+        .iter()
+        .find(|e| e.path == file_path)
+        .ok_or_else(|| ResponseError::new("path_not_found", format!("No embedding found for {}. Run embed_file_intelligence_documents first.", file_path)))?
+        .embedding
+        .clone();
+
+    let k = top_k.unwrap_or(5);
+    let mut ranked: Vec<(String, f32)> = embeddings
+        .iter()
+        .filter(|e| e.path != file_path)
+        .map(|e| (e.path.clone(), 1.0 - cosine_distance(&query, &e.embedding)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    ranked.truncate(k);
+
+    Ok(serde_json::json!({
+        "success": true,
+        "path": file_path,
+        "neighbors": ranked.iter().map(|(path, similarity)| serde_json::json!({
+            "path": path,
+            "similarity": similarity
+        })).collect::<Vec<_>>()
+    }))
+}
+
+// ============================================================================
+// FILE WATCHER COMMANDS
+// ============================================================================
+
+use crate::file_watcher::{FileWatcher, WatchConfig, FileEvent};
+use crate::file_watcher::event_to_document;
+use crate::file_watcher::should_prompt_for_event;
+use crate::file_watcher::SavePrompterConfig;
+
+static FILE_WATCHER: Lazy<Mutex<Option<FileWatcher>>> = Lazy::new(|| Mutex::new(None));
 static WATCHER_EVENTS: Lazy<Mutex<Vec<FileEvent>>> = Lazy::new(|| Mutex::new(Vec::new()));
 static WATCHER_SUGGESTIONS: Lazy<Mutex<Vec<WatcherSuggestion>>> = Lazy::new(|| Mutex::new(Vec::new()));
 static LAST_PROMPT_TIMES: Lazy<Mutex<std::collections::HashMap<String, std::time::Instant>>> = Lazy::new(|| Mutex::new(std::collections::HashMap::new()));
@@ -2325,24 +4252,28 @@ pub struct WatcherSuggestion {
 
 /// Start the file watcher
 #[tauri::command]
-pub async fn start_file_watcher(watch_paths: Option<Vec<String>>) -> Result<serde_json::Value, String> {
+pub async fn start_file_watcher(watch_paths: Option<Vec<String>>) -> Result<serde_json::Value, ResponseError> {
     println!("[FILE_WATCHER] start_file_watcher");
-    
+
     let mut config = WatchConfig::default();
     if let Some(paths) = watch_paths {
         config.paths = paths;
     }
-    
+
     let mut watcher = FileWatcher::new(config.clone());
-    let rx = watcher.start()?;
-    
+    let rx = watcher.start().map_err(|e| ResponseError::new("internal_error", e))?;
+
     // Store the watcher
     {
-        let mut w = FILE_WATCHER.lock().map_err(|e| format!("Lock error: {}", e))?;
+        let mut w = FILE_WATCHER.lock().map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
         *w = Some(watcher);
     }
     
-    // Spawn a thread to collect events
+    ensure_job_workers_started();
+
+    // Spawn a thread to collect events. Analysis itself happens off this
+    // thread, in the job queue's worker pool, so a burst of events can't
+    // block ingestion or get silently dropped.
     std::thread::spawn(move || {
         while let Ok(event) = rx.recv() {
             // Store raw event
@@ -2351,40 +4282,11 @@ pub async fn start_file_watcher(watch_paths: Option<Vec<String>>) -> Result<serd
                 if e.len() > 100 { e.remove(0); }
             }
 
-            // Generate organization suggestions for this event
-            if let Ok(doc) = std::panic::catch_unwind(|| event_to_document(&event)) {
-                let doc = doc;
-                // Grab user prefs
-                let prefs = USER_PREFS
-                    .lock()
-                    .ok()
-                    .map(|p| p.clone())
-                    .unwrap_or_default();
-
-                // Prompt gating (cooldown, event type)
-                let prompter_cfg = SavePrompterConfig::default();
-                let mut last_prompts = LAST_PROMPT_TIMES.lock().ok();
-                let should_prompt = match last_prompts.as_mut() {
-                    Some(map) => should_prompt_for_event(&event, &prompter_cfg, map),
-                    None => true,
-                };
-
-                if should_prompt {
-                    let suggestions = file_intelligence::generate_suggestions(&[doc], &prefs);
-                    if let Some(sugg) = suggestions.into_iter().next() {
-                        if let Ok(mut s) = WATCHER_SUGGESTIONS.lock() {
-                            s.push(WatcherSuggestion { suggestion: sugg, event: event.clone() });
-                            if s.len() > 50 { s.remove(0); }
-                        }
-                        if let Some(map) = last_prompts.as_mut() {
-                            map.insert(event.path.clone(), std::time::Instant::now());
-                        }
-                    }
-                }
-            }
+            record_activity_event(&event.path);
+            enqueue_job(event);
         }
     });
-    
+
     Ok(serde_json::json!({
         "success": true,
         "watching": config.paths,
@@ -2394,13 +4296,13 @@ pub async fn start_file_watcher(watch_paths: Option<Vec<String>>) -> Result<serd
 
 /// Stop the file watcher
 #[tauri::command]
-pub async fn stop_file_watcher() -> Result<serde_json::Value, String> {
+pub async fn stop_file_watcher() -> Result<serde_json::Value, ResponseError> {
     println!("[FILE_WATCHER] stop_file_watcher");
-    
-    let mut watcher_lock = FILE_WATCHER.lock().map_err(|e| format!("Lock error: {}", e))?;
-    
+
+    let mut watcher_lock = FILE_WATCHER.lock().map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
+
     if let Some(ref mut watcher) = *watcher_lock {
-        watcher.stop()?;
+        watcher.stop().map_err(|e| ResponseError::new("internal_error", e))?;
     }
     
     *watcher_lock = None;
@@ -2413,9 +4315,9 @@ pub async fn stop_file_watcher() -> Result<serde_json::Value, String> {
 
 /// Get pending file events
 #[tauri::command]
-pub async fn get_file_events(clear: Option<bool>) -> Result<serde_json::Value, String> {
+pub async fn get_file_events(clear: Option<bool>) -> Result<serde_json::Value, ResponseError> {
     let mut events = WATCHER_EVENTS.lock()
-        .map_err(|e| format!("Lock error: {}", e))?;
+        .map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
     
     let result = events.clone();
     
@@ -2432,9 +4334,9 @@ pub async fn get_file_events(clear: Option<bool>) -> Result<serde_json::Value, S
 
 /// Get pending file organization suggestions
 #[tauri::command]
-pub async fn get_file_suggestions(clear: Option<bool>) -> Result<serde_json::Value, String> {
+pub async fn get_file_suggestions(clear: Option<bool>) -> Result<serde_json::Value, ResponseError> {
     let mut suggestions = WATCHER_SUGGESTIONS.lock()
-        .map_err(|e| format!("Lock error: {}", e))?;
+        .map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
 
     let result = suggestions.clone();
 
@@ -2450,13 +4352,13 @@ pub async fn get_file_suggestions(clear: Option<bool>) -> Result<serde_json::Val
 
 /// Get file watcher status
 #[tauri::command]
-pub async fn get_watcher_status() -> Result<serde_json::Value, String> {
-    let watcher_lock = FILE_WATCHER.lock().map_err(|e| format!("Lock error: {}", e))?;
-    let events = WATCHER_EVENTS.lock().map_err(|e| format!("Lock error: {}", e))?;
-    
+pub async fn get_watcher_status() -> Result<serde_json::Value, ResponseError> {
+    let watcher_lock = FILE_WATCHER.lock().map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
+    let events = WATCHER_EVENTS.lock().map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
+
     let (is_running, paths) = match &*watcher_lock {
         Some(w) => {
-            let state = w.get_state()?;
+            let state = w.get_state().map_err(|e| ResponseError::new("internal_error", e))?;
             (state.is_running, state.watched_paths)
         }
         None => (false, Vec::new()),
@@ -2470,61 +4372,1310 @@ pub async fn get_watcher_status() -> Result<serde_json::Value, String> {
     }))
 }
 
-/// Offline index sync commands
+// ============================================================================
+// ACTIVITY TRENDS
+// ============================================================================
+// `WATCHER_EVENTS` is a flat ring buffer of the last 100 events, which can
+// answer "what just happened" but not "where have I been working lately".
+// This accumulates events into fixed 5-minute time buckets keyed by parent
+// directory and by file extension, with each key's score exponentially
+// decayed per elapsed bucket so a burst of activity fades out instead of
+// staying pinned at its peak once it stops. Events merge into the current
+// bucket in place rather than allocating a record per event.
+
+const ACTIVITY_BUCKET_SECONDS: i64 = 300;
+const ACTIVITY_DECAY_FACTOR: f64 = 0.85;
+const ACTIVITY_PRUNE_THRESHOLD: f64 = 0.05;
+const ACTIVITY_PRUNE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ActivityScore {
+    pub score: f64,
+    pub last_bucket: i64,
+    pub last_seen: String,
+}
+
+#[derive(Default)]
+struct ActivityTrends {
+    directories: HashMap<String, ActivityScore>,
+    extensions: HashMap<String, ActivityScore>,
+}
+
+static ACTIVITY_TRENDS: Lazy<Mutex<ActivityTrends>> = Lazy::new(|| Mutex::new(ActivityTrends::default()));
+static ACTIVITY_PRUNER_STARTED: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+
+fn now_epoch_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn current_activity_bucket() -> i64 {
+    now_epoch_secs() / ACTIVITY_BUCKET_SECONDS
+}
+
+/// Merge one occurrence of `key` into `bucket`: decay whatever score is
+/// already there by one decay step per bucket that's elapsed since it was
+/// last touched, then add this occurrence's weight.
+fn bump_activity_score(map: &mut HashMap<String, ActivityScore>, key: String, bucket: i64) {
+    let entry = map.entry(key).or_insert(ActivityScore { score: 0.0, last_bucket: bucket, last_seen: now_timestamp() });
+    let elapsed = (bucket - entry.last_bucket).max(0) as i32;
+    entry.score = entry.score * ACTIVITY_DECAY_FACTOR.powi(elapsed) + 1.0;
+    entry.last_bucket = bucket;
+    entry.last_seen = now_timestamp();
+}
+
+/// Record one file-watcher event into the trend maps, keyed by its parent
+/// directory and its extension (via `chunking::extension_of`, which already
+/// strips the synthetic `#row=N` suffix structured-document paths carry).
+fn record_activity_event(path: &str) {
+    let bucket = current_activity_bucket();
+    let dir_key = Path::new(path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|| "/".to_string());
+    let ext_key = chunking::extension_of(path);
+
+    if let Ok(mut trends) = ACTIVITY_TRENDS.lock() {
+        bump_activity_score(&mut trends.directories, dir_key, bucket);
+        if !ext_key.is_empty() {
+            bump_activity_score(&mut trends.extensions, ext_key, bucket);
+        }
+    }
+
+    ensure_activity_pruner_started();
+}
+
+/// Decay every key up to the current bucket and drop whatever falls below
+/// `ACTIVITY_PRUNE_THRESHOLD`, so directories/extensions nobody's touched in
+/// a while stop cluttering the trend list.
+fn prune_activity_trends() {
+    let bucket = current_activity_bucket();
+    if let Ok(mut trends) = ACTIVITY_TRENDS.lock() {
+        for map in [&mut trends.directories, &mut trends.extensions] {
+            for score in map.values_mut() {
+                let elapsed = (bucket - score.last_bucket).max(0) as i32;
+                score.score *= ACTIVITY_DECAY_FACTOR.powi(elapsed);
+                score.last_bucket = bucket;
+            }
+            map.retain(|_, score| score.score >= ACTIVITY_PRUNE_THRESHOLD);
+        }
+    }
+}
+
+/// Start the periodic pruner, once per process.
+fn ensure_activity_pruner_started() {
+    let mut started = match ACTIVITY_PRUNER_STARTED.lock() {
+        Ok(g) => g,
+        Err(e) => e.into_inner(),
+    };
+    if *started {
+        return;
+    }
+    *started = true;
+    std::thread::spawn(|| loop {
+        std::thread::sleep(ACTIVITY_PRUNE_INTERVAL);
+        prune_activity_trends();
+    });
+}
+
+/// Hottest directories/extensions by decayed activity score, for "you've
+/// been saving a lot here lately - want a rule?" prompts driven by sustained
+/// momentum rather than a single event.
+#[tauri::command]
+pub async fn get_activity_trends(top_k: Option<usize>) -> Result<serde_json::Value, ResponseError> {
+    let k = top_k.unwrap_or(10);
+    let bucket = current_activity_bucket();
+
+    let trends = ACTIVITY_TRENDS.lock().map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
+
+    let rank = |map: &HashMap<String, ActivityScore>| -> Vec<serde_json::Value> {
+        let mut entries: Vec<(String, f64, String)> = map
+            .iter()
+            .map(|(key, score)| {
+                let elapsed = (bucket - score.last_bucket).max(0) as i32;
+                let decayed = score.score * ACTIVITY_DECAY_FACTOR.powi(elapsed);
+                (key.clone(), decayed, score.last_seen.clone())
+            })
+            .collect();
+        entries.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        entries.truncate(k);
+        entries
+            .into_iter()
+            .map(|(key, score, last_seen)| serde_json::json!({
+                "key": key,
+                "score": score,
+                "last_seen": last_seen
+            }))
+            .collect()
+    };
+
+    Ok(serde_json::json!({
+        "success": true,
+        "directories": rank(&trends.directories),
+        "extensions": rank(&trends.extensions)
+    }))
+}
+
+// ============================================================================
+// PERSISTENT JOB QUEUE
+// ============================================================================
+// `start_file_watcher` used to run `event_to_document` + `generate_suggestions`
+// inline on its event-collection thread, so a burst of saves could block
+// ingestion and the fixed-size event/suggestion Vecs would silently drop
+// whatever didn't fit. Each file event is now handed off as a durable job
+// (queued/running/done/failed, with an attempt count and backoff) that a
+// small worker pool pulls from, so bursts get queued instead of dropped and
+// a crash or restart resumes whatever was still pending.
+
+const JOB_QUEUE_CONCURRENCY: usize = 2;
+const JOB_QUEUE_MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Job {
+    pub id: String,
+    pub event: FileEvent,
+    pub status: JobStatus,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+static JOB_QUEUE: Lazy<Mutex<Vec<Job>>> = Lazy::new(|| Mutex::new(load_job_queue()));
+static JOB_WORKERS_STARTED: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+// Captured once in `ensure_job_workers_started` (called from the async
+// `start_file_watcher` command, so a tokio runtime is current at that point)
+// so the plain `std::thread`-based job workers can still call into
+// provider-aware async embedding via `Handle::block_on`.
+static JOB_TOKIO_HANDLE: Lazy<Mutex<Option<tokio::runtime::Handle>>> = Lazy::new(|| Mutex::new(None));
+
+fn job_queue_path() -> std::path::PathBuf {
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .unwrap_or_else(|_| ".".to_string());
+    Path::new(&home).join(".wayfinder").join("job_queue.json")
+}
+
+/// Load the persisted job queue, resuming pending work across a crash or
+/// restart. Any job still marked `Running` from a previous process (which is
+/// no longer actually running it) is reset to `Queued` so a worker picks it
+/// back up.
+fn load_job_queue() -> Vec<Job> {
+    let mut jobs: Vec<Job> = fs::read_to_string(job_queue_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    for job in jobs.iter_mut() {
+        if job.status == JobStatus::Running {
+            job.status = JobStatus::Queued;
+        }
+    }
+    jobs
+}
+
+fn persist_job_queue(jobs: &[Job]) {
+    let path = job_queue_path();
+    if let Some(parent) = path.parent() {
+        if fs::create_dir_all(parent).is_err() {
+            return;
+        }
+    }
+    if let Ok(json) = serde_json::to_string_pretty(jobs) {
+        let _ = fs::write(path, json);
+    }
+}
+
+fn now_timestamp() -> String {
+    Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Enqueue an "analyze/organize" job for `event`, to be picked up by the
+/// worker pool started by `ensure_job_workers_started`.
+fn enqueue_job(event: FileEvent) -> String {
+    let id = format!("job-{:016x}", rand::thread_rng().gen::<u64>());
+    let job = Job {
+        id: id.clone(),
+        event,
+        status: JobStatus::Queued,
+        attempts: 0,
+        last_error: None,
+        next_attempt_at: now_timestamp(),
+        created_at: now_timestamp(),
+        updated_at: now_timestamp(),
+    };
+    if let Ok(mut jobs) = JOB_QUEUE.lock() {
+        jobs.push(job);
+        persist_job_queue(&jobs);
+    }
+    id
+}
+
+/// Start the bounded worker pool, once per process. Safe to call on every
+/// `start_file_watcher`; later calls are no-ops.
+fn ensure_job_workers_started() {
+    let mut started = match JOB_WORKERS_STARTED.lock() {
+        Ok(g) => g,
+        Err(e) => e.into_inner(),
+    };
+    if *started {
+        return;
+    }
+    *started = true;
+    if let Ok(mut handle) = JOB_TOKIO_HANDLE.lock() {
+        *handle = Some(tokio::runtime::Handle::current());
+    }
+    for _ in 0..JOB_QUEUE_CONCURRENCY {
+        std::thread::spawn(job_worker_loop);
+    }
+}
+
+/// One worker: repeatedly claims the next eligible job (queued, or a
+/// failed-but-retryable job whose backoff has elapsed) and runs it.
+fn job_worker_loop() {
+    loop {
+        let claimed_id = {
+            let mut jobs = match JOB_QUEUE.lock() {
+                Ok(g) => g,
+                Err(e) => e.into_inner(),
+            };
+            let now = now_timestamp();
+            let pos = jobs.iter().position(|j| j.status == JobStatus::Queued && j.next_attempt_at <= now);
+            pos.map(|i| {
+                jobs[i].status = JobStatus::Running;
+                jobs[i].updated_at = now_timestamp();
+                let id = jobs[i].id.clone();
+                persist_job_queue(&jobs);
+                id
+            })
+        };
+
+        match claimed_id {
+            Some(id) => run_job(&id),
+            None => std::thread::sleep(std::time::Duration::from_millis(500)),
+        }
+    }
+}
+
+/// Run the analysis a single job represents (the same `event_to_document` +
+/// `generate_suggestions` work `start_file_watcher` used to do inline), then
+/// record its outcome: done, requeued with backoff, or failed outright once
+/// `JOB_QUEUE_MAX_ATTEMPTS` is exhausted.
+fn run_job(job_id: &str) {
+    let event = {
+        let jobs = match JOB_QUEUE.lock() {
+            Ok(g) => g,
+            Err(e) => e.into_inner(),
+        };
+        jobs.iter().find(|j| j.id == job_id).map(|j| j.event.clone())
+    };
+    let Some(event) = event else { return };
+
+    let outcome: Result<(), String> = std::panic::catch_unwind(|| event_to_document(&event))
+        .map_err(|_| "event_to_document panicked".to_string())
+        .map(|doc| {
+            // `LAST_PROMPT_TIMES` stays a plain in-process map rather than
+            // moving into `STORE`: `should_prompt_for_event` keys it on
+            // `std::time::Instant`, a monotonic clock reading with no fixed
+            // epoch, so it can't round-trip a restart meaningfully anyway.
+            let prefs = STORE.get_preferences(&current_scan_root_key());
+
+            let prompter_cfg = SavePrompterConfig::default();
+            let mut last_prompts = LAST_PROMPT_TIMES.lock().ok();
+            let should_prompt = match last_prompts.as_mut() {
+                Some(map) => should_prompt_for_event(&event, &prompter_cfg, map),
+                None => true,
+            };
+
+            if should_prompt {
+                let suggestions = file_intelligence::generate_suggestions(std::slice::from_ref(&doc), &prefs);
+                if let Some(sugg) = suggestions.into_iter().next() {
+                    if let Ok(mut s) = WATCHER_SUGGESTIONS.lock() {
+                        s.push(WatcherSuggestion { suggestion: sugg, event: event.clone() });
+                        if s.len() > 50 { s.remove(0); }
+                    }
+                    if let Some(map) = last_prompts.as_mut() {
+                        map.insert(event.path.clone(), std::time::Instant::now());
+                    }
+                }
+            }
+
+            reembed_document_incremental(&doc);
+        });
+
+    if let Ok(mut jobs) = JOB_QUEUE.lock() {
+        if let Some(job) = jobs.iter_mut().find(|j| j.id == job_id) {
+            job.updated_at = now_timestamp();
+            match outcome {
+                Ok(()) => {
+                    job.status = JobStatus::Done;
+                    job.last_error = None;
+                }
+                Err(e) => {
+                    job.attempts += 1;
+                    job.last_error = Some(e);
+                    if job.attempts >= JOB_QUEUE_MAX_ATTEMPTS {
+                        job.status = JobStatus::Failed;
+                    } else {
+                        job.status = JobStatus::Queued;
+                        let backoff_secs = 2u64.saturating_pow(job.attempts);
+                        job.next_attempt_at = (Local::now() + chrono::Duration::seconds(backoff_secs as i64))
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string();
+                    }
+                }
+            }
+        }
+        persist_job_queue(&jobs);
+    }
+}
+
+/// Report the job queue's current state, for the UI to poll.
+#[tauri::command]
+pub async fn get_job_queue_status() -> Result<serde_json::Value, ResponseError> {
+    let jobs = JOB_QUEUE.lock().map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
+    let queued = jobs.iter().filter(|j| j.status == JobStatus::Queued).count();
+    let running = jobs.iter().filter(|j| j.status == JobStatus::Running).count();
+    let done = jobs.iter().filter(|j| j.status == JobStatus::Done).count();
+    let failed = jobs.iter().filter(|j| j.status == JobStatus::Failed).count();
+
+    Ok(serde_json::json!({
+        "success": true,
+        "queued": queued,
+        "running": running,
+        "done": done,
+        "failed": failed,
+        "jobs": &*jobs
+    }))
+}
+
+/// Re-queue a failed job for another attempt, resetting its attempt count.
 #[tauri::command]
-pub async fn cache_index_locally(index_dir: String, cache_dir: String) -> Result<bool, String> {
-    // Call Python backend offline.py:cache_index_locally
-    let output = std::process::Command::new("python")
-        .arg("-m")
-        .arg("md_scanner.offline")
-        .arg("cache_index_locally")
-        .arg(&index_dir)
-        .arg(&cache_dir)
-        .output()
-        .map_err(|e| format!("Failed to launch Python: {}", e))?;
-    if output.status.success() {
-        Ok(true)
-    } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
+pub async fn retry_job(job_id: String) -> Result<serde_json::Value, ResponseError> {
+    let mut jobs = JOB_QUEUE.lock().map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
+    let job = jobs.iter_mut().find(|j| j.id == job_id).ok_or_else(|| ResponseError::new("job_not_found", format!("No job with id {}", job_id)))?;
+    if job.status == JobStatus::Running {
+        return Err(ResponseError::new("job_running", "Job is currently running"));
     }
+    job.status = JobStatus::Queued;
+    job.attempts = 0;
+    job.last_error = None;
+    job.next_attempt_at = now_timestamp();
+    job.updated_at = now_timestamp();
+    persist_job_queue(&jobs);
+    Ok(serde_json::json!({ "success": true, "message": format!("Job {} re-queued", job_id) }))
 }
 
+/// Cancel a pending or failed job, removing it from the queue. A job that is
+/// currently running can't be cancelled out from under its worker.
 #[tauri::command]
-pub async fn export_index(index_dir: String, export_path: String) -> Result<bool, String> {
-    let output = std::process::Command::new("python")
-        .arg("-m")
-        .arg("md_scanner.offline")
-        .arg("export_index")
-        .arg(&index_dir)
-        .arg(&export_path)
-        .output()
-        .map_err(|e| format!("Failed to launch Python: {}", e))?;
-    if output.status.success() {
-        Ok(true)
+pub async fn cancel_job(job_id: String) -> Result<serde_json::Value, ResponseError> {
+    let mut jobs = JOB_QUEUE.lock().map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
+    let job = jobs.iter().find(|j| j.id == job_id).ok_or_else(|| ResponseError::new("job_not_found", format!("No job with id {}", job_id)))?;
+    if job.status == JobStatus::Running {
+        return Err(ResponseError::new("job_running", "Cannot cancel a job while it is running"));
+    }
+    jobs.retain(|j| j.id != job_id);
+    persist_job_queue(&jobs);
+    Ok(serde_json::json!({ "success": true, "message": format!("Job {} cancelled", job_id) }))
+}
+
+// ============================================================================
+// INCREMENTAL INDEX WATCHER
+// ============================================================================
+// A second, independent `FileWatcher` instance (distinct from the
+// file-intelligence watcher above) that keeps `index.json`/`embeddings.json`
+// fresh as files change, instead of requiring a full manual re-scan.
+
+static INDEX_WATCHER: Lazy<Mutex<Option<FileWatcher>>> = Lazy::new(|| Mutex::new(None));
+static INDEX_WATCHER_PROGRESS: Lazy<Mutex<BatchProgress>> = Lazy::new(|| {
+    Mutex::new(BatchProgress {
+        batch_id: "index-watcher".to_string(),
+        total_files: 0,
+        processed_files: 0,
+        current_batch: 0,
+        total_batches: 0,
+        batch_size: 1,
+        status: "stopped".to_string(),
+        started_at: String::new(),
+        last_updated: String::new(),
+        errors: Vec::new(),
+    })
+});
+
+const INDEX_WATCHER_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Re-chunk and re-embed a single changed path, replacing its entries in
+/// `index.json` and `embeddings.json` in place. Skipped (no-op) if none of
+/// the recomputed chunk hashes differ from what's already stored, so a
+/// save that doesn't change content doesn't churn the index.
+fn reindex_single_path(index_data: &mut IndexData, embeddings: &mut Vec<FileEmbedding>, path: &str) -> Result<bool, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("Failed to stat {}: {}", path, e))?;
+    let content = read_indexed_content(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let ext = chunking::extension_of(path);
+    let chunks = chunking::chunk_text(&content, &ext);
+
+    let mut new_hashes: Vec<String> = chunks.iter().map(|c| content_hash(&c.text)).collect();
+    new_hashes.sort();
+    let mut old_hashes: Vec<String> = embeddings.iter().filter(|fe| fe.path == path).map(|fe| fe.content_hash.clone()).collect();
+    old_hashes.sort();
+    if !old_hashes.is_empty() && old_hashes == new_hashes {
+        return Ok(false);
+    }
+
+    // Local fallback only; provider-aware (Azure/GCP/Ollama) re-embed on
+    // watcher events is out of scope for this pass.
+    let dim = 512usize;
+    let new_embeddings: Vec<FileEmbedding> = chunks
+        .into_iter()
+        .map(|chunk| FileEmbedding {
+            path: path.to_string(),
+            embedding: deterministic_embedding(&chunk.text, dim),
+            content_hash: content_hash(&chunk.text),
+            start_byte: chunk.start_byte,
+            end_byte: chunk.end_byte,
+        })
+        .collect();
+
+    embeddings.retain(|fe| fe.path != path);
+    embeddings.extend(new_embeddings);
+
+    let modified = DateTime::<Local>::from(metadata.modified().map_err(|e| e.to_string())?)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+    let name = Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or("unknown").to_string();
+    let image_info = if is_image_extension(&ext) {
+        scan_image_info(Path::new(path))
+    } else {
+        None
+    };
+    index_data.files.retain(|f| f.path != path);
+    index_data.files.push(FileEntry {
+        path: path.to_string(),
+        name,
+        size: metadata.len(),
+        modified,
+        extension: ext,
+        image_width: image_info.as_ref().map(|i| i.width),
+        image_height: image_info.as_ref().map(|i| i.height),
+        blurhash: image_info.map(|i| i.blurhash),
+    });
+
+    Ok(true)
+}
+
+/// Prune a deleted path's entries from `index.json` and `embeddings.json`.
+fn prune_deleted_path(index_data: &mut IndexData, embeddings: &mut Vec<FileEmbedding>, path: &str) -> bool {
+    let before = index_data.files.len();
+    index_data.files.retain(|f| f.path != path);
+    embeddings.retain(|fe| fe.path != path);
+    index_data.files.len() != before
+}
+
+/// Start the incremental index watcher: subscribes to filesystem events
+/// under `index_data.scan_path`, coalesces them on a debounce window, and
+/// incrementally updates the index instead of requiring a full re-scan.
+#[tauri::command]
+pub async fn start_index_watcher(index_dir: String) -> Result<serde_json::Value, ResponseError> {
+    let index_path = Path::new(&index_dir).to_path_buf();
+    let index_file = index_path.join("index.json");
+    if !index_file.exists() {
+        return Err(ResponseError::new("path_not_found", "Index not found. Please scan a directory first."));
+    }
+    let index_str = fs::read_to_string(&index_file).map_err(|e| ResponseError::new("internal_error", format!("Failed to read index: {}", e)))?;
+    let scan_path: String = serde_json::from_str::<IndexData>(&index_str)
+        .map_err(|e| ResponseError::new("config_parse_error", format!("Failed to parse index: {}", e)))?
+        .scan_path;
+
+    let mut config = WatchConfig::default();
+    config.paths = vec![scan_path.clone()];
+
+    let mut watcher = FileWatcher::new(config.clone());
+    let rx = watcher.start().map_err(|e| ResponseError::new("internal_error", e))?;
+
+    {
+        let mut w = INDEX_WATCHER.lock().map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
+        *w = Some(watcher);
+    }
+    {
+        let mut progress = INDEX_WATCHER_PROGRESS.lock().map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
+        progress.status = "running".to_string();
+        progress.started_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        progress.processed_files = 0;
+        progress.errors.clear();
+    }
+
+    std::thread::spawn(move || {
+        let mut pending: HashMap<String, std::time::Instant> = HashMap::new();
+        loop {
+            // Drain whatever events arrived since the last flush, resetting
+            // each path's debounce timer so a burst of saves on one file
+            // only triggers one re-index once things go quiet.
+            while let Ok(event) = rx.try_recv() {
+                pending.insert(event.path.clone(), std::time::Instant::now());
+            }
+
+            // Stop the loop once the watcher has been torn down.
+            let still_running = INDEX_WATCHER.lock().map(|w| w.is_some()).unwrap_or(false);
+            if !still_running {
+                break;
+            }
+
+            let ready: Vec<String> = pending
+                .iter()
+                .filter(|(_, t)| t.elapsed() >= INDEX_WATCHER_DEBOUNCE)
+                .map(|(p, _)| p.clone())
+                .collect();
+
+            if !ready.is_empty() {
+                for path in &ready {
+                    pending.remove(path);
+                }
+                if let Err(e) = flush_index_watcher_batch(&index_path, &ready) {
+                    if let Ok(mut progress) = INDEX_WATCHER_PROGRESS.lock() {
+                        progress.errors.push(e);
+                        if progress.errors.len() > 50 { progress.errors.remove(0); }
+                    }
+                }
+            }
+
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+    });
+
+    Ok(serde_json::json!({
+        "success": true,
+        "watching": scan_path,
+        "message": "Index watcher started"
+    }))
+}
+
+/// Apply one debounced batch of changed paths to the on-disk index.
+fn flush_index_watcher_batch(index_path: &Path, changed_paths: &[String]) -> Result<(), String> {
+    let index_file = index_path.join("index.json");
+    let embeddings_file = index_path.join("embeddings.json");
+
+    let mut index_data: IndexData = serde_json::from_str(
+        &fs::read_to_string(&index_file).map_err(|e| format!("Failed to read index: {}", e))?,
+    )
+    .map_err(|e| format!("Failed to parse index: {}", e))?;
+
+    let mut embeddings_data: EmbeddingsData = if embeddings_file.exists() {
+        serde_json::from_str(&fs::read_to_string(&embeddings_file).map_err(|e| e.to_string())?)
+            .unwrap_or(EmbeddingsData { embeddings: Vec::new(), model: "Deterministic".to_string(), created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string() })
     } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
+        EmbeddingsData { embeddings: Vec::new(), model: "Deterministic".to_string(), created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string() }
+    };
+
+    let mut changed_count = 0usize;
+    for path in changed_paths {
+        let result = if Path::new(path).exists() {
+            reindex_single_path(&mut index_data, &mut embeddings_data.embeddings, path)
+        } else {
+            Ok(prune_deleted_path(&mut index_data, &mut embeddings_data.embeddings, path))
+        };
+        match result {
+            Ok(true) => changed_count += 1,
+            Ok(false) => {}
+            Err(e) => log_error(index_path, "index_watcher", Some(path), &e, None),
+        }
+    }
+
+    if changed_count > 0 {
+        index_data.created_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        fs::write(&index_file, serde_json::to_string_pretty(&index_data).map_err(|e| e.to_string())?)
+            .map_err(|e| format!("Failed to write index: {}", e))?;
+        embeddings_data.created_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        fs::write(&embeddings_file, serde_json::to_string_pretty(&embeddings_data).map_err(|e| e.to_string())?)
+            .map_err(|e| format!("Failed to write embeddings: {}", e))?;
+        if let Err(e) = write_inverted_index(index_path, &index_data.files) {
+            log_error(index_path, "index_watcher", None, &e, None);
+        }
+    }
+
+    if let Ok(mut progress) = INDEX_WATCHER_PROGRESS.lock() {
+        progress.processed_files += changed_count;
+        progress.last_updated = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    }
+
+    Ok(())
+}
+
+/// Stop the incremental index watcher.
+#[tauri::command]
+pub async fn stop_index_watcher() -> Result<serde_json::Value, ResponseError> {
+    let mut watcher_lock = INDEX_WATCHER.lock().map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
+    if let Some(ref mut watcher) = *watcher_lock {
+        watcher.stop().map_err(|e| ResponseError::new("internal_error", e))?;
+    }
+    *watcher_lock = None;
+
+    if let Ok(mut progress) = INDEX_WATCHER_PROGRESS.lock() {
+        progress.status = "stopped".to_string();
+    }
+
+    Ok(serde_json::json!({
+        "success": true,
+        "message": "Index watcher stopped"
+    }))
+}
+
+/// Get the incremental index watcher's live progress, for the UI to poll.
+#[tauri::command]
+pub async fn get_index_watcher_status() -> Result<serde_json::Value, ResponseError> {
+    let progress = INDEX_WATCHER_PROGRESS.lock().map_err(|e| ResponseError::new("lock_error", format!("Lock error: {}", e)))?;
+    Ok(serde_json::to_value(&*progress).unwrap())
+}
+
+// Index storage backends: lets an index's snapshot (index.json, clusters.json,
+// azure_config.json) live on the local filesystem or in an S3-compatible
+// bucket. Mirrors the `EmbeddingProvider` trait's shape - one trait, one
+// config enum persisted per index, implementors picked at call time rather
+// than compiled in. The S3 implementor signs requests itself (SigV4 over
+// `reqwest`) rather than pulling in the full `aws-sdk-s3` crate tree, the
+// same "hand-roll the REST call" choice already made for Azure/GCP.
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexStoreBackend {
+    Local,
+    S3,
+}
+
+impl Default for IndexStoreBackend {
+    fn default() -> Self {
+        IndexStoreBackend::Local
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct S3StoreConfig {
+    pub bucket: String,
+    #[serde(default)]
+    pub key_prefix: String,
+    pub region: String,
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub path_style: bool,
+    pub access_key_id: String,
+    // Never written to the config file - lives in the OS keychain, or its
+    // encrypted fallback file (see `store_secret`), same treatment as
+    // `AzureConfig.api_key`.
+    #[serde(default, skip_serializing)]
+    pub secret_access_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct IndexStoreConfig {
+    #[serde(default)]
+    pub backend: IndexStoreBackend,
+    #[serde(default)]
+    pub s3: Option<S3StoreConfig>,
+}
+
+fn index_store_config_path(index_path: &Path) -> std::path::PathBuf {
+    index_path.join("store_config.json")
+}
+
+/// The files that make up a portable snapshot of an index. Deliberately
+/// excludes `embeddings.json`/`inverted.json`/caches: those are large and
+/// fully regenerable from `index.json`, the same size-vs-regenerability
+/// tradeoff the embedding cache already makes.
+const SNAPSHOT_FILES: &[&str] = &["index.json", "clusters.json", "azure_config.json"];
+
+/// A place a whole-index snapshot can be pushed to or pulled from.
+#[async_trait]
+pub trait IndexStore: Send + Sync {
+    /// Upload `data` under `key`, creating or overwriting it.
+    async fn put_object(&self, key: &str, data: Vec<u8>) -> Result<(), String>;
+    /// Download the object at `key`, if it exists.
+    async fn get_object(&self, key: &str) -> Result<Option<Vec<u8>>, String>;
+}
+
+pub struct LocalFsIndexStore {
+    root: std::path::PathBuf,
+}
+
+#[async_trait]
+impl IndexStore for LocalFsIndexStore {
+    async fn put_object(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(&path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let path = self.root.join(key);
+        match fs::read(&path) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+        }
+    }
+}
+
+pub struct S3IndexStore {
+    config: S3StoreConfig,
+    client: reqwest::Client,
+}
+
+impl S3IndexStore {
+    /// The virtual-host or path-style base URL for this bucket, per
+    /// `config.path_style` and an optional custom (e.g. MinIO) endpoint.
+    fn base_url(&self) -> String {
+        let host = self
+            .config
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", self.config.region));
+        if self.config.path_style {
+            format!("{}/{}", host.trim_end_matches('/'), self.config.bucket)
+        } else {
+            let host = host.trim_start_matches("https://").trim_start_matches("http://");
+            format!("https://{}.{}", self.config.bucket, host)
+        }
+    }
+
+    fn object_key(&self, key: &str) -> String {
+        if self.config.key_prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", self.config.key_prefix.trim_end_matches('/'), key)
+        }
+    }
+
+    /// Sign and send one request with AWS SigV4 (the same scheme S3
+    /// understands regardless of region or custom endpoint).
+    async fn send_signed(&self, method: reqwest::Method, key: &str, body: Vec<u8>) -> Result<reqwest::Response, String> {
+        use hmac::{Hmac, Mac};
+        use sha2::{Digest, Sha256};
+        type HmacSha256 = Hmac<Sha256>;
+
+        let object_key = self.object_key(key);
+        let url = format!("{}/{}", self.base_url(), object_key);
+        let parsed = reqwest::Url::parse(&url).map_err(|e| format!("Invalid S3 URL: {}", e))?;
+        let host = parsed.host_str().ok_or("S3 URL has no host")?.to_string();
+
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date_stamp = now.format("%Y%m%d").to_string();
+
+        let mut hasher = Sha256::new();
+        hasher.update(&body);
+        let payload_hash = secret_hex_encode(&hasher.finalize());
+
+        let canonical_uri = parsed.path().to_string();
+        let canonical_headers = format!("host:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n", host, payload_hash, amz_date);
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n\n{}\n{}\n{}",
+            method.as_str(),
+            canonical_uri,
+            canonical_headers,
+            signed_headers,
+            payload_hash
+        );
+
+        let mut cr_hasher = Sha256::new();
+        cr_hasher.update(canonical_request.as_bytes());
+        let canonical_request_hash = secret_hex_encode(&cr_hasher.finalize());
+
+        let credential_scope = format!("{}/{}/s3/aws4_request", date_stamp, self.config.region);
+        let string_to_sign =
+            format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, credential_scope, canonical_request_hash);
+
+        let sign = |key: &[u8], msg: &str| -> Vec<u8> {
+            let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+            mac.update(msg.as_bytes());
+            mac.finalize().into_bytes().to_vec()
+        };
+        let k_date = sign(format!("AWS4{}", self.config.secret_access_key).as_bytes(), &date_stamp);
+        let k_region = sign(&k_date, &self.config.region);
+        let k_service = sign(&k_region, "s3");
+        let k_signing = sign(&k_service, "aws4_request");
+        let signature = secret_hex_encode(&sign(&k_signing, &string_to_sign));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.config.access_key_id, credential_scope, signed_headers, signature
+        );
+
+        self.client
+            .request(method, &url)
+            .header("host", host)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", &payload_hash)
+            .header("authorization", authorization)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("S3 request failed: {}", e))
+    }
+}
+
+#[async_trait]
+impl IndexStore for S3IndexStore {
+    async fn put_object(&self, key: &str, data: Vec<u8>) -> Result<(), String> {
+        let resp = self.send_signed(reqwest::Method::PUT, key, data).await?;
+        if resp.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("S3 PUT {} failed: {}", key, resp.status()))
+        }
+    }
+
+    async fn get_object(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let resp = self.send_signed(reqwest::Method::GET, key, Vec::new()).await?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !resp.status().is_success() {
+            return Err(format!("S3 GET {} failed: {}", key, resp.status()));
+        }
+        resp.bytes().await.map(|b| Some(b.to_vec())).map_err(|e| format!("Failed to read S3 response body: {}", e))
+    }
+}
+
+/// Resolve the configured store for `index_path`, falling back to a local
+/// store rooted at `index_path` itself when no `store_config.json` exists
+/// or it names the local backend.
+async fn build_index_store(index_path: &Path) -> Result<Box<dyn IndexStore>, String> {
+    let config: IndexStoreConfig = fs::read_to_string(index_store_config_path(index_path))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+
+    match config.backend {
+        IndexStoreBackend::Local => Ok(Box::new(LocalFsIndexStore { root: index_path.to_path_buf() })),
+        IndexStoreBackend::S3 => {
+            let mut s3_config = config.s3.ok_or("Index store is configured for S3 but has no S3 settings")?;
+            if s3_config.secret_access_key.is_empty() {
+                s3_config.secret_access_key = load_secret(index_path, "s3", "secret_access_key");
+            }
+            let client = reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(120))
+                .build()
+                .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+            Ok(Box::new(S3IndexStore { config: s3_config, client }))
+        }
+    }
+}
+
+/// Persist the backend (local or S3) an index syncs through, storing the S3
+/// secret key in the OS keychain rather than in `store_config.json`.
+#[tauri::command]
+pub async fn configure_index_store(index_dir: String, backend: IndexStoreBackend, s3: Option<S3StoreConfig>) -> Result<bool, String> {
+    let index_path = Path::new(&index_dir);
+    let mut s3_to_save = s3.clone();
+    if let Some(ref config) = s3 {
+        if !config.secret_access_key.is_empty() {
+            store_secret(index_path, "s3", "secret_access_key", &config.secret_access_key)?;
+        }
+        if let Some(ref mut saved) = s3_to_save {
+            saved.secret_access_key = String::new();
+        }
+    }
+
+    let config = IndexStoreConfig { backend, s3: s3_to_save };
+    let json = serde_json::to_string_pretty(&config).map_err(|e| format!("Failed to serialize store config: {}", e))?;
+    fs::write(index_store_config_path(index_path), json).map_err(|e| format!("Failed to write store config: {}", e))?;
+    Ok(true)
+}
+
+/// Zip up the portable part of an index (`SNAPSHOT_FILES`) into one buffer.
+/// Files that don't exist yet (e.g. no Azure config saved) are skipped.
+fn build_index_snapshot_zip(index_path: &Path) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::new();
+    {
+        let cursor = std::io::Cursor::new(&mut buf);
+        let mut writer = zip::ZipWriter::new(cursor);
+        let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for name in SNAPSHOT_FILES {
+            let path = index_path.join(name);
+            let Ok(contents) = fs::read(&path) else { continue };
+            writer.start_file(*name, options).map_err(|e| format!("Failed to add {} to snapshot: {}", name, e))?;
+            std::io::Write::write_all(&mut writer, &contents).map_err(|e| format!("Failed to write {} to snapshot: {}", name, e))?;
+        }
+        writer.finish().map_err(|e| format!("Failed to finalize snapshot zip: {}", e))?;
     }
+    Ok(buf)
+}
+
+/// Reverse of `build_index_snapshot_zip`: extract a snapshot into `target_dir`.
+fn extract_index_snapshot_zip(zip_bytes: &[u8], target_dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(target_dir).map_err(|e| format!("Failed to create {}: {}", target_dir.display(), e))?;
+    let cursor = std::io::Cursor::new(zip_bytes);
+    let mut archive = zip::ZipArchive::new(cursor).map_err(|e| format!("Failed to open snapshot zip: {}", e))?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| format!("Failed to read snapshot entry: {}", e))?;
+        // `enclosed_name()` rejects absolute paths and `..` components, so a
+        // malicious entry (e.g. `../../../../home/user/.bashrc`) in a zip
+        // pulled from a remote store or an arbitrary local path can't escape
+        // `target_dir` (zip-slip).
+        let Some(relative_path) = entry.enclosed_name() else {
+            return Err(format!("Snapshot entry has an unsafe path: {}", entry.name()));
+        };
+        let dest = target_dir.join(relative_path);
+        if entry.is_dir() {
+            fs::create_dir_all(&dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?;
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let mut contents = Vec::new();
+        std::io::Read::read_to_end(&mut entry, &mut contents).map_err(|e| format!("Failed to read snapshot entry: {}", e))?;
+        fs::write(&dest, contents).map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Build a snapshot of `index_dir` and write it to `export_path`, then also
+/// push it as `snapshot.zip` to the index's configured store: the S3 bucket
+/// when one is configured, otherwise `index_dir` itself (the local store).
+#[tauri::command]
+pub async fn export_index(index_dir: String, export_path: String) -> Result<bool, String> {
+    let index_path = Path::new(&index_dir);
+    let zip_bytes = build_index_snapshot_zip(index_path)?;
+    fs::write(&export_path, &zip_bytes).map_err(|e| format!("Failed to write {}: {}", export_path, e))?;
+
+    let store = build_index_store(index_path).await?;
+    store.put_object("snapshot.zip", zip_bytes).await?;
+    Ok(true)
 }
 
+/// Restore a snapshot into `target_dir`: from `zip_path` if given, otherwise
+/// pulled from `target_dir`'s configured store (local or S3).
 #[tauri::command]
 pub async fn import_index(zip_path: String, target_dir: String) -> Result<bool, String> {
-    let output = std::process::Command::new("python")
-        .arg("-m")
-        .arg("md_scanner.offline")
-        .arg("import_index")
-        .arg(&zip_path)
-        .arg(&target_dir)
-        .output()
-        .map_err(|e| format!("Failed to launch Python: {}", e))?;
-    if output.status.success() {
-        Ok(true)
+    let target_path = Path::new(&target_dir);
+    if !zip_path.is_empty() {
+        let zip_bytes = fs::read(&zip_path).map_err(|e| format!("Failed to read {}: {}", zip_path, e))?;
+        extract_index_snapshot_zip(&zip_bytes, target_path)?;
+        return Ok(true);
+    }
+
+    let store = build_index_store(target_path).await?;
+    let zip_bytes = store
+        .get_object("snapshot.zip")
+        .await?
+        .ok_or("No snapshot.zip found in the configured store")?;
+    extract_index_snapshot_zip(&zip_bytes, target_path)?;
+    Ok(true)
+}
+
+/// Pull an index's remote snapshot down into a local cache directory. Falls
+/// back to a same-machine copy when `index_dir` has no remote store
+/// configured, so this still works for a purely local index.
+#[tauri::command]
+pub async fn cache_index_locally(index_dir: String, cache_dir: String) -> Result<bool, String> {
+    let index_path = Path::new(&index_dir);
+    let store = build_index_store(index_path).await?;
+    let zip_bytes = match store.get_object("snapshot.zip").await? {
+        Some(bytes) => bytes,
+        None => build_index_snapshot_zip(index_path)?,
+    };
+    extract_index_snapshot_zip(&zip_bytes, Path::new(&cache_dir))?;
+    Ok(true)
+}
+
+// Embedded HTTP REST API, so an index can be queried without going through
+// Tauri at all (external scripts, editors). The server listens on
+// 127.0.0.1 only, so other machines on the network cannot reach it.
+// Handlers below call straight into the same `search`/`get_stats`/etc.
+// functions the Tauri commands use, so there is exactly one implementation
+// of each operation.
+use axum::{
+    extract::{Query as AxumQuery, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json},
+    routing::get,
+    Router,
+};
+use std::sync::Arc;
+
+struct HttpApiState {
+    index_dir: String,
+    bearer_token: Option<String>,
+}
+
+struct HttpServerHandle {
+    port: u16,
+    shutdown: tokio::sync::oneshot::Sender<()>,
+}
+
+static HTTP_SERVER: Lazy<Mutex<Option<HttpServerHandle>>> = Lazy::new(|| Mutex::new(None));
+
+/// The one place every migrated command's `Result<Value, ResponseError>`
+/// actually reaches the outside world through the embedded HTTP API (a
+/// direct Tauri IPC call serializes its own Result without passing through
+/// here), so it's where `Response<T>` earns its keep: success and failure
+/// both go out through the same `{success, data, error}` envelope.
+fn json_result<E: Into<ResponseError>>(result: Result<serde_json::Value, E>) -> axum::response::Response {
+    match result {
+        Ok(value) => (StatusCode::OK, Json(Response::ok(value))).into_response(),
+        Err(err) => {
+            let err: ResponseError = err.into();
+            let status = StatusCode::from_u16(err.http_status).unwrap_or(StatusCode::BAD_REQUEST);
+            (status, Json(Response::<serde_json::Value>::err(err))).into_response()
+        }
+    }
+}
+
+fn check_bearer_token(state: &HttpApiState, headers: &header::HeaderMap) -> Result<(), axum::response::Response> {
+    let Some(expected) = &state.bearer_token else { return Ok(()) };
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if provided == Some(expected.as_str()) {
+        Ok(())
     } else {
-        Err(String::from_utf8_lossy(&output.stderr).to_string())
+        Err((StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Missing or invalid bearer token" }))).into_response())
+    }
+}
+
+#[derive(Deserialize)]
+struct HttpSearchQuery {
+    q: String,
+    top_k: Option<usize>,
+    semantic_weight: Option<f32>,
+    max_typos: Option<usize>,
+}
+
+async fn http_search(
+    State(state): State<Arc<HttpApiState>>,
+    headers: header::HeaderMap,
+    AxumQuery(params): AxumQuery<HttpSearchQuery>,
+) -> axum::response::Response {
+    if let Err(resp) = check_bearer_token(&state, &headers) {
+        return resp;
+    }
+    json_result(
+        search(
+            params.q,
+            state.index_dir.clone(),
+            params.top_k.unwrap_or(20),
+            params.semantic_weight.unwrap_or(0.5),
+            params.max_typos,
+            None,
+        )
+        .await,
+    )
+}
+
+async fn http_stats(State(state): State<Arc<HttpApiState>>, headers: header::HeaderMap) -> axum::response::Response {
+    if let Err(resp) = check_bearer_token(&state, &headers) {
+        return resp;
+    }
+    json_result(get_stats(state.index_dir.clone()).await)
+}
+
+#[derive(Deserialize)]
+struct HttpTimelineQuery {
+    days: Option<usize>,
+}
+
+async fn http_timeline(
+    State(state): State<Arc<HttpApiState>>,
+    headers: header::HeaderMap,
+    AxumQuery(params): AxumQuery<HttpTimelineQuery>,
+) -> axum::response::Response {
+    if let Err(resp) = check_bearer_token(&state, &headers) {
+        return resp;
+    }
+    json_result(get_timeline(state.index_dir.clone(), params.days.unwrap_or(7), None).await)
+}
+
+async fn http_clusters(State(state): State<Arc<HttpApiState>>, headers: header::HeaderMap) -> axum::response::Response {
+    if let Err(resp) = check_bearer_token(&state, &headers) {
+        return resp;
+    }
+    json_result(get_clusters_summary(state.index_dir.clone()).await)
+}
+
+async fn http_validate(State(state): State<Arc<HttpApiState>>, headers: header::HeaderMap) -> axum::response::Response {
+    if let Err(resp) = check_bearer_token(&state, &headers) {
+        return resp;
+    }
+    json_result(validate_index(state.index_dir.clone()).await)
+}
+
+/// Start the embedded HTTP REST API (`/search`, `/stats`, `/timeline`,
+/// `/clusters`, `/validate`), mounting the same handler functions the Tauri
+/// commands call. Only one server runs at a time; calling this again while
+/// one is already up returns an error rather than silently leaking a second
+/// listener.
+#[tauri::command]
+pub async fn start_http_server(index_dir: String, port: u16, bearer_token: Option<String>) -> Result<serde_json::Value, String> {
+    {
+        let guard = HTTP_SERVER.lock().map_err(|e| format!("Lock error: {}", e))?;
+        if guard.is_some() {
+            return Err("HTTP server is already running. Stop it first.".to_string());
+        }
     }
+
+    let state = Arc::new(HttpApiState { index_dir, bearer_token });
+    let app = Router::new()
+        .route("/search", get(http_search))
+        .route("/stats", get(http_stats))
+        .route("/timeline", get(http_timeline))
+        .route("/clusters", get(http_clusters))
+        .route("/validate", get(http_validate))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(|e| format!("Failed to bind port {}: {}", port, e))?;
+    let bound_port = listener.local_addr().map_err(|e| e.to_string())?.port();
+
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app)
+            .with_graceful_shutdown(async { let _ = shutdown_rx.await; })
+            .await;
+    });
+
+    let mut guard = HTTP_SERVER.lock().map_err(|e| format!("Lock error: {}", e))?;
+    *guard = Some(HttpServerHandle { port: bound_port, shutdown: shutdown_tx });
+
+    Ok(serde_json::json!({
+        "success": true,
+        "port": bound_port,
+        "message": format!("HTTP API listening on 127.0.0.1:{}", bound_port)
+    }))
 }
 
-// Add md5 helper that returns Digest for easy hex formatting
-fn md5_hash(s: &str) -> md5::Digest {
-    md5::compute(s)
+/// Stop the embedded HTTP REST API, if running.
+#[tauri::command]
+pub async fn stop_http_server() -> Result<serde_json::Value, String> {
+    let handle = {
+        let mut guard = HTTP_SERVER.lock().map_err(|e| format!("Lock error: {}", e))?;
+        guard.take()
+    };
+    match handle {
+        Some(handle) => {
+            let _ = handle.shutdown.send(());
+            Ok(serde_json::json!({ "success": true, "message": format!("HTTP API on port {} stopped", handle.port) }))
+        }
+        None => Ok(serde_json::json!({ "success": true, "message": "HTTP API was not running" })),
+    }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typo_budget_respects_length_bands() {
+        assert_eq!(typo_budget(1), 0);
+        assert_eq!(typo_budget(4), 0);
+        assert_eq!(typo_budget(5), 1);
+        assert_eq!(typo_budget(8), 1);
+        assert_eq!(typo_budget(9), 2);
+        assert_eq!(typo_budget(100), 2);
+    }
+
+    #[test]
+    fn bounded_levenshtein_finds_distance_within_budget() {
+        assert_eq!(bounded_levenshtein("abc", "abc", 1), Some(0));
+        assert_eq!(bounded_levenshtein("abc", "abd", 1), Some(1));
+        assert_eq!(bounded_levenshtein("kitten", "sitting", 3), Some(3));
+    }
+
+    #[test]
+    fn bounded_levenshtein_bails_out_past_budget() {
+        // Every character differs, so the true distance (3) exceeds max (1).
+        assert_eq!(bounded_levenshtein("abc", "xyz", 1), None);
+        // Length gap alone (7) already exceeds max (1).
+        assert_eq!(bounded_levenshtein("a", "abcdefgh", 1), None);
+    }
+
+    #[tokio::test]
+    async fn ann_search_recalls_exact_match_as_top_result() {
+        let dir = std::env::temp_dir().join(format!("wayfinder_hnsw_test_{}", std::process::id()));
+        let _ = fs::create_dir_all(&dir);
+
+        let vectors: Vec<Vec<f32>> = (0..20)
+            .map(|i| vec![i as f32, (i * 2) as f32, (20 - i) as f32])
+            .collect();
+        let embeddings_data = EmbeddingsData {
+            embeddings: vectors
+                .iter()
+                .enumerate()
+                .map(|(i, v)| FileEmbedding {
+                    path: format!("file_{}.txt", i),
+                    embedding: v.clone(),
+                    content_hash: format!("hash_{}", i),
+                    start_byte: 0,
+                    end_byte: 0,
+                })
+                .collect(),
+            model: "test".to_string(),
+            created_at: "test".to_string(),
+        };
+        fs::write(dir.join("embeddings.json"), serde_json::to_string(&embeddings_data).unwrap()).unwrap();
+
+        build_ann_index(dir.to_string_lossy().to_string()).await.unwrap();
+
+        let target = vectors[7].clone();
+        let result = ann_search(dir.to_string_lossy().to_string(), target, 1, None).await.unwrap();
+        let top_path = result["results"][0]["path"].as_str().unwrap();
+        assert_eq!(top_path, "file_7.txt");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn build_zip_with_entry(entry_name: &str, contents: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        {
+            let cursor = std::io::Cursor::new(&mut buf);
+            let mut writer = zip::ZipWriter::new(cursor);
+            let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+            writer.start_file(entry_name, options).unwrap();
+            std::io::Write::write_all(&mut writer, contents).unwrap();
+            writer.finish().unwrap();
+        }
+        buf
+    }
+
+    #[test]
+    fn extract_index_snapshot_zip_rejects_path_traversal() {
+        let dir = std::env::temp_dir().join(format!("wayfinder_zipslip_test_{}", std::process::id()));
+        let _ = fs::create_dir_all(&dir);
+
+        let malicious = build_zip_with_entry("../../../../tmp/wayfinder_zipslip_escaped.txt", b"pwned");
+        let result = extract_index_snapshot_zip(&malicious, &dir);
+        assert!(result.is_err());
+        assert!(!Path::new("/tmp/wayfinder_zipslip_escaped.txt").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn extract_index_snapshot_zip_writes_well_behaved_entries() {
+        let dir = std::env::temp_dir().join(format!("wayfinder_zipok_test_{}", std::process::id()));
+        let _ = fs::create_dir_all(&dir);
+
+        let well_behaved = build_zip_with_entry("index.json", b"{}");
+        extract_index_snapshot_zip(&well_behaved, &dir).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("index.json")).unwrap(), "{}");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,138 @@
+// Structured document ingestion for tabular and log-dump formats.
+//
+// `scan_directory` otherwise treats every file as one opaque text blob, which
+// turns a 50k-row CSV or a JSONL dump into a single index entry with no
+// per-row searchability. For `.csv`, `.tsv`, `.jsonl`, and `.ndjson` files,
+// this module splits the file into individual records and flattens each one
+// to `field: value` text, so embeddings and search address specific rows
+// (`data.csv#row=42`) rather than whole files.
+
+/// A single record parsed out of a structured document, with the 1-based
+/// row number it came from (the header row is not counted for CSV/TSV).
+pub struct StructuredRecord {
+    pub row: usize,
+    pub text: String,
+}
+
+/// Whether `ext` (lowercase, no dot) is handled by this module.
+pub fn is_structured_extension(ext: &str) -> bool {
+    matches!(ext, "csv" | "tsv" | "jsonl" | "ndjson")
+}
+
+/// Build the synthetic per-record path used to address a row in search
+/// results and embeddings, e.g. `data.csv#row=42`.
+pub fn synthetic_path(base_path: &str, row: usize) -> String {
+    format!("{}#row={}", base_path, row)
+}
+
+/// Split a synthetic path back into its base file path and row number.
+/// Returns `None` for ordinary (non-synthetic) paths.
+pub fn split_synthetic_path(path: &str) -> Option<(&str, usize)> {
+    let (base, row_part) = path.rsplit_once("#row=")?;
+    let row: usize = row_part.parse().ok()?;
+    Some((base, row))
+}
+
+fn delimiter_for(ext: &str) -> char {
+    if ext == "tsv" {
+        '\t'
+    } else {
+        ','
+    }
+}
+
+/// Naive delimited-line splitter with basic double-quote handling. Good
+/// enough for well-formed exports; it does not handle escaped quotes inside
+/// quoted fields.
+fn split_delimited_line(line: &str, delim: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in line.chars() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if c == delim && !in_quotes {
+            fields.push(current.trim().to_string());
+            current = String::new();
+        } else {
+            current.push(c);
+        }
+    }
+    fields.push(current.trim().to_string());
+    fields
+}
+
+fn flatten_json(value: &serde_json::Value, prefix: &str, out: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (k, v) in map {
+                let key = if prefix.is_empty() { k.clone() } else { format!("{}.{}", prefix, k) };
+                flatten_json(v, &key, out);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for (i, v) in items.iter().enumerate() {
+                let key = format!("{}.{}", prefix, i);
+                flatten_json(v, &key, out);
+            }
+        }
+        serde_json::Value::Null => out.push(format!("{}: ", prefix)),
+        other => out.push(format!("{}: {}", prefix, other)),
+    }
+}
+
+fn parse_csv_like(content: &str, ext: &str) -> Vec<StructuredRecord> {
+    let delim = delimiter_for(ext);
+    let mut lines = content.lines();
+    let headers = match lines.next() {
+        Some(h) => split_delimited_line(h, delim),
+        None => return Vec::new(),
+    };
+
+    let mut records = Vec::new();
+    for (i, line) in lines.enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_delimited_line(line, delim);
+        let text = headers
+            .iter()
+            .zip(fields.iter())
+            .map(|(h, v)| format!("{}: {}", h, v))
+            .collect::<Vec<_>>()
+            .join("\n");
+        records.push(StructuredRecord { row: i + 1, text });
+    }
+    records
+}
+
+fn parse_jsonl_like(content: &str) -> Vec<StructuredRecord> {
+    let mut records = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else { continue };
+        let mut fields = Vec::new();
+        flatten_json(&value, "", &mut fields);
+        records.push(StructuredRecord { row: i + 1, text: fields.join("\n") });
+    }
+    records
+}
+
+/// Parse `content` (the full contents of a file with the given lowercase
+/// extension) into individually addressable records.
+pub fn parse_records(content: &str, ext: &str) -> Vec<StructuredRecord> {
+    match ext {
+        "csv" | "tsv" => parse_csv_like(content, ext),
+        "jsonl" | "ndjson" => parse_jsonl_like(content),
+        _ => Vec::new(),
+    }
+}
+
+/// Re-parse a structured file and return the flattened text for a single
+/// row, for callers (search, embedding) that only have a synthetic path.
+pub fn record_text_at(content: &str, ext: &str, row: usize) -> Option<String> {
+    parse_records(content, ext).into_iter().find(|r| r.row == row).map(|r| r.text)
+}